@@ -0,0 +1,68 @@
+use crate::{flight, MaxAcceleration};
+use bevy::prelude::*;
+use heron::*;
+use sebaka::steering;
+
+/// Steer around colliders in the way, on top of whatever behaviour the entity has
+#[derive(Component)]
+pub struct ObstacleAvoidance {
+    /// How far ahead to look, in seconds of travel at the current speed
+    pub look_ahead: f32,
+}
+
+impl Default for ObstacleAvoidance {
+    fn default() -> Self {
+        Self { look_ahead: 1.5 }
+    }
+}
+
+/// Cast a ray along the velocity and add a lateral avoidance to the steering output when it hits something
+///
+/// Avoidance has priority: the behaviour only keeps the thrust it leaves.
+/// It is applied after the transition blend and the jerk limit, so that it takes effect at once.
+pub fn avoid_obstacles(
+    physics_world: PhysicsWorld,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &mut Acceleration,
+            &ObstacleAvoidance,
+            Option<&MaxAcceleration>,
+            Option<&flight::InertiaDampener>,
+        ),
+        Without<flight::ManualControl>,
+    >,
+) {
+    for (entity, transform, velocity, mut acceleration, avoidance, max_acceleration, dampener) in
+        &mut query
+    {
+        // A still ship has nothing ahead, which also lets it settle next to an obstacle
+        let ray = velocity.linear * avoidance.look_ahead;
+        if ray.length_squared() <= f32::EPSILON {
+            continue;
+        }
+
+        let hit = physics_world.ray_cast_with_filter(
+            transform.translation,
+            ray,
+            true,
+            CollisionLayers::default(),
+            |hit_entity| hit_entity != entity,
+        );
+        if let Some(hit) = hit {
+            let max_acceleration =
+                flight::available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+            let avoiding = steering::avoidance(
+                velocity.linear,
+                hit.normal,
+                hit.collision_point.distance(transform.translation),
+                ray.length(),
+                max_acceleration,
+            );
+            acceleration.linear =
+                steering::prioritize(avoiding, acceleration.linear, max_acceleration);
+        }
+    }
+}
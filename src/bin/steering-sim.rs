@@ -0,0 +1,133 @@
+//! Headless reference simulation of the steering behaviours, printing the trajectory as CSV.
+//!
+//! Usage: cargo run --bin steering-sim -- [behaviour] [euler|verlet] [key=value...]
+//! Behaviours: seek, arrive, flee, persue, evade, interpose, path, hide, offset
+//! Keys: distance, max_velocity, max_acceleration, dt, duration, wind (constant crosswind acceleration along x)
+//!
+//! Targets stand still at `distance` ahead, the steering functions having no notion of time to move them:
+//! - interpose steers between the target and a second one `distance` to the right,
+//! - path goes through a waypoint halfway to the right before ending on the target,
+//! - hide hides from the target behind an obstacle halfway to it,
+//! - offset holds a slot 200 to the right of the target seen as a leader.
+
+use bevy::prelude::*;
+use sebaka::steering::{self, Integrator, Kinematics};
+use std::{cell::Cell, env, process};
+
+/// Radius of the obstacle used by the hide behaviour
+const OBSTACLE_RADIUS: f32 = 100.;
+
+fn main() {
+    let mut behaviour = "arrive".to_string();
+    let mut integrator = Integrator::SemiImplicitEuler;
+    let mut distance = 5000.;
+    let mut max_velocity = 1000.;
+    let mut max_acceleration = 100.;
+    let mut dt = 1. / 60.;
+    let mut duration = 60.;
+    let mut wind = 0.;
+
+    for arg in env::args().skip(1) {
+        match arg.split_once('=') {
+            None => match arg.as_str() {
+                "seek" | "arrive" | "flee" | "persue" | "evade" | "interpose" | "path" | "hide"
+                | "offset" => behaviour = arg,
+                "euler" => integrator = Integrator::SemiImplicitEuler,
+                "verlet" => integrator = Integrator::VelocityVerlet,
+                _ => exit_with_usage(&format!("unknown argument '{}'", arg)),
+            },
+            Some((key, value)) => {
+                let value = value
+                    .parse::<f32>()
+                    .unwrap_or_else(|_| exit_with_usage(&format!("invalid number '{}'", value)));
+                match key {
+                    "distance" => distance = value,
+                    "max_velocity" => max_velocity = value,
+                    "max_acceleration" => max_acceleration = value,
+                    "dt" => dt = value,
+                    "duration" => duration = value,
+                    "wind" => wind = value,
+                    _ => exit_with_usage(&format!("unknown parameter '{}'", key)),
+                }
+            }
+        }
+    }
+
+    let target = Vec3::Y * distance;
+    let still = |position| Kinematics {
+        position,
+        velocity: Vec3::ZERO,
+    };
+    let path = [Vec3::new(distance / 2., distance / 2., 0.), target];
+    let path_index = Cell::new(0);
+    // A single obstacle gives a single spot, wherever the agent is
+    let hiding_spot =
+        steering::hiding_spot(Vec3::ZERO, target, [(target / 2., OBSTACLE_RADIUS)]).unwrap();
+
+    // The wind is not known to the steering, which has to compensate for it through the velocity error
+    let wind = Vec3::X * wind;
+    let samples = steering::simulate(integrator, Kinematics::default(), dt, duration, |agent| {
+        wind + match behaviour.as_str() {
+            "seek" => steering::seek(agent, target, max_velocity, max_acceleration),
+            "flee" => steering::flee(agent, target, max_velocity, max_acceleration),
+            "persue" => {
+                steering::persue(agent, still(target), None, max_velocity, max_acceleration)
+            }
+            "evade" => steering::evade(
+                agent,
+                still(target),
+                steering::DEFAULT_EVADE_DISTANCE,
+                max_velocity,
+                max_acceleration,
+            ),
+            "interpose" => steering::interpose(
+                agent,
+                still(target),
+                still(Vec3::X * distance),
+                max_velocity,
+                max_acceleration,
+            ),
+            "path" => {
+                let (acceleration, index) = steering::follow_path(
+                    agent,
+                    &path,
+                    path_index.get(),
+                    steering::PATH_ARRIVAL_RADIUS,
+                    max_velocity,
+                    max_acceleration,
+                );
+                path_index.set(index);
+                acceleration
+            }
+            "hide" => steering::arrive(agent, hiding_spot, max_velocity, max_acceleration),
+            "offset" => steering::offset_pursuit(
+                agent,
+                still(target),
+                Quat::IDENTITY,
+                Vec3::X * 200.,
+                max_velocity,
+                max_acceleration,
+            ),
+            _ => steering::arrive(agent, target, max_velocity, max_acceleration),
+        }
+    });
+
+    println!("time,x,y,speed,acceleration,distance");
+    for sample in samples {
+        println!(
+            "{:.4},{:.3},{:.3},{:.3},{:.3},{:.3}",
+            sample.time,
+            sample.position.x,
+            sample.position.y,
+            sample.velocity.length(),
+            sample.acceleration.length(),
+            sample.position.distance(target)
+        );
+    }
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("steering-sim: {}", error);
+    eprintln!("usage: steering-sim [seek|arrive|flee|persue|evade|interpose|path|hide|offset] [euler|verlet] [distance=5000] [max_velocity=1000] [max_acceleration=100] [dt=0.0167] [duration=60] [wind=0]");
+    process::exit(1)
+}
@@ -0,0 +1,418 @@
+use crate::localization::Localization;
+use bevy::prelude::*;
+
+/// Something the player can do with a key or a mouse button
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Order,
+    Strafe,
+    CommandedSpeed,
+    HoldPosition,
+    BuildMode,
+    PlaceStation,
+    Cancel,
+    ThrustForward,
+    ThrustBackward,
+    ThrustLeft,
+    ThrustRight,
+    ManualFlight,
+    FlightAssist,
+    InertiaDampener,
+    Pan,
+    Zoom,
+    DebugFrame,
+    DebugDrawPreset,
+    MotionStretch,
+    DebugColorMode,
+    ExhaustProfile,
+    Telemetry,
+    StripChart,
+    SolarWind,
+    Language,
+    KeyReference,
+}
+
+impl Action {
+    pub fn category(self) -> ActionCategory {
+        match self {
+            Action::Order
+            | Action::Strafe
+            | Action::CommandedSpeed
+            | Action::HoldPosition
+            | Action::BuildMode
+            | Action::PlaceStation
+            | Action::Cancel => ActionCategory::Orders,
+            Action::ThrustForward
+            | Action::ThrustBackward
+            | Action::ThrustLeft
+            | Action::ThrustRight
+            | Action::ManualFlight
+            | Action::FlightAssist
+            | Action::InertiaDampener => ActionCategory::Flight,
+            Action::Pan | Action::Zoom => ActionCategory::Camera,
+            Action::DebugFrame
+            | Action::DebugDrawPreset
+            | Action::MotionStretch
+            | Action::DebugColorMode
+            | Action::ExhaustProfile
+            | Action::Telemetry
+            | Action::StripChart
+            | Action::SolarWind => ActionCategory::Debug,
+            Action::Language | Action::KeyReference => ActionCategory::Interface,
+        }
+    }
+
+    /// Localization key of the action name
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Action::Order => "action.order",
+            Action::Strafe => "action.strafe",
+            Action::CommandedSpeed => "action.commanded_speed",
+            Action::HoldPosition => "action.hold_position",
+            Action::BuildMode => "action.build_mode",
+            Action::PlaceStation => "action.place_station",
+            Action::Cancel => "action.cancel",
+            Action::ThrustForward => "action.thrust_forward",
+            Action::ThrustBackward => "action.thrust_backward",
+            Action::ThrustLeft => "action.thrust_left",
+            Action::ThrustRight => "action.thrust_right",
+            Action::ManualFlight => "action.manual_flight",
+            Action::FlightAssist => "action.flight_assist",
+            Action::InertiaDampener => "action.inertia_dampener",
+            Action::Pan => "action.pan",
+            Action::Zoom => "action.zoom",
+            Action::DebugFrame => "action.debug_frame",
+            Action::DebugDrawPreset => "action.debug_draw_preset",
+            Action::MotionStretch => "action.motion_stretch",
+            Action::DebugColorMode => "action.debug_color_mode",
+            Action::ExhaustProfile => "action.exhaust_profile",
+            Action::Telemetry => "action.telemetry",
+            Action::StripChart => "action.strip_chart",
+            Action::SolarWind => "action.solar_wind",
+            Action::Language => "action.language",
+            Action::KeyReference => "action.key_reference",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ActionCategory {
+    Orders,
+    Flight,
+    Camera,
+    Debug,
+    Interface,
+}
+
+impl ActionCategory {
+    pub const ALL: [ActionCategory; 5] = [
+        ActionCategory::Orders,
+        ActionCategory::Flight,
+        ActionCategory::Camera,
+        ActionCategory::Debug,
+        ActionCategory::Interface,
+    ];
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            ActionCategory::Orders => "category.orders",
+            ActionCategory::Flight => "category.flight",
+            ActionCategory::Camera => "category.camera",
+            ActionCategory::Debug => "category.debug",
+            ActionCategory::Interface => "category.interface",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Mouse wheel, optionally while holding Ctrl
+    Wheel {
+        ctrl: bool,
+    },
+}
+
+impl Binding {
+    /// Human readable name of the binding
+    pub fn label(self, localization: &Localization) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(MouseButton::Left) => localization.get("input.mouse_left").to_string(),
+            Binding::Mouse(MouseButton::Right) => localization.get("input.mouse_right").to_string(),
+            Binding::Mouse(MouseButton::Middle) => {
+                localization.get("input.mouse_middle").to_string()
+            }
+            Binding::Mouse(MouseButton::Other(button)) => {
+                format!("{} {}", localization.get("input.mouse"), button)
+            }
+            Binding::Wheel { ctrl: false } => localization.get("input.wheel").to_string(),
+            Binding::Wheel { ctrl: true } => format!("Ctrl + {}", localization.get("input.wheel")),
+        }
+    }
+}
+
+/// Current binding of every action, systems read their keys from here so that remaps apply everywhere
+pub struct KeyBindings {
+    bindings: Vec<(Action, Binding)>,
+}
+
+impl KeyBindings {
+    pub fn iter(&self) -> impl Iterator<Item = (Action, Binding)> + '_ {
+        self.bindings.iter().copied()
+    }
+
+    pub fn get(&self, action: Action) -> Option<Binding> {
+        self.iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, binding)| binding)
+    }
+
+    /// Bind `action`, warning when the binding is shared with another action since both would trigger at once
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        for (other, _) in self
+            .bindings
+            .iter()
+            .filter(|(other, bound)| *other != action && *bound == binding)
+        {
+            warn!(
+                "{:?} is bound to {:?}, which is already bound to {:?}",
+                action, binding, other
+            );
+        }
+
+        match self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+            Some((_, bound)) => *bound = binding,
+            None => self.bindings.push((action, binding)),
+        }
+    }
+
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        match self.get(action) {
+            Some(Binding::Key(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    pub fn button(&self, action: Action) -> Option<MouseButton> {
+        match self.get(action) {
+            Some(Binding::Mouse(button)) => Some(button),
+            _ => None,
+        }
+    }
+
+    /// Whether mouse wheel events currently go to `action`, Ctrl being held exactly when its wheel binding asks for it
+    pub fn wheel_active(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        match self.get(action) {
+            Some(Binding::Wheel { ctrl }) => {
+                ctrl == keys.any_pressed([KeyCode::LControl, KeyCode::RControl])
+            }
+            _ => false,
+        }
+    }
+
+    pub fn pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        self.key(action).map_or(false, |key| keys.pressed(key))
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        self.key(action).map_or(false, |key| keys.just_pressed(key))
+    }
+
+    pub fn button_just_pressed(&self, buttons: &Input<MouseButton>, action: Action) -> bool {
+        self.button(action)
+            .map_or(false, |button| buttons.just_pressed(button))
+    }
+
+    pub fn button_just_released(&self, buttons: &Input<MouseButton>, action: Action) -> bool {
+        self.button(action)
+            .map_or(false, |button| buttons.just_released(button))
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: vec![
+                (Action::Order, Binding::Mouse(MouseButton::Right)),
+                (Action::Strafe, Binding::Key(KeyCode::LAlt)),
+                (Action::CommandedSpeed, Binding::Wheel { ctrl: true }),
+                (Action::HoldPosition, Binding::Key(KeyCode::H)),
+                (Action::BuildMode, Binding::Key(KeyCode::B)),
+                (Action::PlaceStation, Binding::Mouse(MouseButton::Left)),
+                (Action::Cancel, Binding::Key(KeyCode::Escape)),
+                (Action::ThrustForward, Binding::Key(KeyCode::W)),
+                (Action::ThrustBackward, Binding::Key(KeyCode::S)),
+                (Action::ThrustLeft, Binding::Key(KeyCode::A)),
+                (Action::ThrustRight, Binding::Key(KeyCode::D)),
+                (Action::ManualFlight, Binding::Key(KeyCode::M)),
+                (Action::FlightAssist, Binding::Key(KeyCode::X)),
+                (Action::InertiaDampener, Binding::Key(KeyCode::Z)),
+                (Action::Pan, Binding::Mouse(MouseButton::Middle)),
+                (Action::Zoom, Binding::Wheel { ctrl: false }),
+                (Action::DebugFrame, Binding::Key(KeyCode::F3)),
+                (Action::DebugDrawPreset, Binding::Key(KeyCode::F4)),
+                (Action::MotionStretch, Binding::Key(KeyCode::F5)),
+                (Action::DebugColorMode, Binding::Key(KeyCode::F2)),
+                (Action::ExhaustProfile, Binding::Key(KeyCode::F7)),
+                (Action::Telemetry, Binding::Key(KeyCode::F8)),
+                (Action::StripChart, Binding::Key(KeyCode::F6)),
+                (Action::SolarWind, Binding::Key(KeyCode::F10)),
+                (Action::Language, Binding::Key(KeyCode::F9)),
+                (Action::KeyReference, Binding::Key(KeyCode::F1)),
+            ],
+        }
+    }
+}
+
+/// Whether the key reference overlay is shown, it does not capture any input besides its own key and Esc
+#[derive(Default)]
+pub struct KeyReference {
+    pub visible: bool,
+}
+
+#[derive(Component)]
+pub struct KeyReferenceOverlay;
+
+/// Show or hide the key reference with F1, Esc also hides it
+pub fn toggle_key_reference(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    asset_server: Res<AssetServer>,
+    localization: Res<Localization>,
+    mut key_reference: ResMut<KeyReference>,
+    overlay_query: Query<Entity, With<KeyReferenceOverlay>>,
+) {
+    let toggle = bindings.just_pressed(&keys, Action::KeyReference);
+    let dismiss = key_reference.visible && bindings.just_pressed(&keys, Action::Cancel);
+    if !toggle && !dismiss {
+        return;
+    }
+
+    for overlay in &overlay_query {
+        commands.entity(overlay).despawn_recursive();
+    }
+
+    key_reference.visible = !key_reference.visible;
+    if key_reference.visible {
+        // Built on every opening, so that remaps and language changes are always reflected
+        spawn_key_reference(&mut commands, &asset_server, &localization, &bindings);
+    }
+}
+
+fn spawn_key_reference(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    localization: &Localization,
+    bindings: &KeyBindings,
+) {
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+    let heading_style = TextStyle {
+        font: font.clone(),
+        font_size: 20.,
+        color: Color::rgb(1., 0.8, 0.3),
+    };
+    let entry_style = TextStyle {
+        font,
+        font_size: 16.,
+        color: Color::WHITE,
+    };
+
+    // Categories are split in two columns, balanced on their number of lines
+    let sections: Vec<(ActionCategory, Vec<String>)> = ActionCategory::ALL
+        .iter()
+        .map(|&category| {
+            let entries = bindings
+                .iter()
+                .filter(|(action, _)| action.category() == category)
+                .map(|(action, binding)| {
+                    format!(
+                        "{}  —  {}",
+                        binding.label(localization),
+                        localization.get(action.name_key())
+                    )
+                })
+                .collect();
+            (category, entries)
+        })
+        .filter(|(_, entries): &(_, Vec<String>)| !entries.is_empty())
+        .collect();
+    let total_lines: usize = sections.iter().map(|(_, entries)| entries.len() + 1).sum();
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(KeyReferenceOverlay)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        max_size: Size::new(Val::Percent(90.), Val::Percent(90.)),
+                        padding: UiRect::all(Val::Px(16.)),
+                        ..default()
+                    },
+                    color: Color::rgba(0., 0., 0., 0.7).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let mut lines = 0;
+                    let mut sections = sections.into_iter().peekable();
+                    for column in 0..2 {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::ColumnReverse,
+                                    margin: UiRect::all(Val::Px(16.)),
+                                    ..default()
+                                },
+                                color: Color::NONE.into(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                while let Some((category, entries)) = sections.peek() {
+                                    // The first column takes about half of the lines, the second one the rest
+                                    if column == 0
+                                        && lines > 0
+                                        && lines + entries.len() / 2 > total_lines / 2
+                                    {
+                                        break;
+                                    }
+                                    lines += entries.len() + 1;
+
+                                    parent.spawn_bundle(TextBundle::from_section(
+                                        localization.get(category.name_key()),
+                                        heading_style.clone(),
+                                    ));
+                                    parent.spawn_bundle(
+                                        TextBundle::from_section(
+                                            entries.join("\n"),
+                                            entry_style.clone(),
+                                        )
+                                        .with_style(
+                                            Style {
+                                                margin: UiRect {
+                                                    bottom: Val::Px(12.),
+                                                    ..default()
+                                                },
+                                                ..default()
+                                            },
+                                        ),
+                                    );
+                                    sections.next();
+                                }
+                            });
+                    }
+                });
+        });
+}
@@ -0,0 +1,166 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    zones::SpeedZone,
+    CursorMode, MouseWorldPosition,
+};
+use bevy::prelude::*;
+use heron::*;
+use sebaka::steering::Obstacle;
+
+/// Radius of a station, used for both its collider and placement checks
+pub const STATION_RADIUS: f32 = 400.;
+
+/// Half extent of the square area where stations can be built
+pub const WORLD_HALF_EXTENT: f32 = 100_000.;
+
+/// Radius of the speed limited zone around a station
+pub const STATION_SPEED_ZONE_RADIUS: f32 = 2_000.;
+
+/// Speed limit around stations
+pub const STATION_SPEED_LIMIT: f32 = 150.;
+
+#[derive(Component)]
+pub struct Station;
+
+/// Semi-transparent station following the cursor while in build mode
+#[derive(Component)]
+pub struct StationGhost {
+    valid: bool,
+}
+
+/// Enter build mode with B, leave it with B, right click or Esc
+pub fn toggle_build_mode(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    bindings: Res<KeyBindings>,
+    mut cursor_mode: ResMut<CursorMode>,
+    ghost_query: Query<Entity, With<StationGhost>>,
+) {
+    let next_mode = match *cursor_mode {
+        CursorMode::Orders if bindings.just_pressed(&keys, Action::BuildMode) => CursorMode::Build,
+        CursorMode::Build
+            if bindings.just_pressed(&keys, Action::BuildMode)
+                || bindings.just_pressed(&keys, Action::Cancel)
+                || bindings.button_just_released(&buttons, Action::Order) =>
+        {
+            CursorMode::Orders
+        }
+        _ => return,
+    };
+
+    if next_mode == CursorMode::Build {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(STATION_RADIUS * 2.)),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(StationGhost { valid: false });
+    } else {
+        for ghost in &ghost_query {
+            commands.entity(ghost).despawn();
+        }
+    }
+
+    *cursor_mode = next_mode;
+}
+
+/// Move the station ghost under the cursor and tint it according to placement validity
+pub fn update_station_ghost(
+    mouse_world_position: Res<MouseWorldPosition>,
+    mut ghost_query: Query<(&mut Transform, &mut Sprite, &mut StationGhost)>,
+    obstacle_query: Query<(&Transform, &CollisionShape), Without<StationGhost>>,
+) {
+    for (mut transform, mut sprite, mut ghost) in &mut ghost_query {
+        if let Some(position) = mouse_world_position.0 {
+            transform.translation = position;
+        }
+
+        ghost.valid = is_valid_placement(transform.translation, &obstacle_query);
+        sprite.color = if ghost.valid {
+            Color::rgba(0.2, 1.0, 0.2, 0.5)
+        } else {
+            Color::rgba(1.0, 0.2, 0.2, 0.5)
+        };
+    }
+}
+
+/// Place a station where the ghost stands on left click, if the placement is valid
+pub fn place_station(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    bindings: Res<KeyBindings>,
+    ghost_query: Query<(&Transform, &StationGhost)>,
+) {
+    if !bindings.button_just_pressed(&buttons, Action::PlaceStation) {
+        return;
+    }
+
+    for (transform, ghost) in &ghost_query {
+        if ghost.valid {
+            spawn_station(&mut commands, transform.translation);
+        }
+    }
+}
+
+pub fn spawn_station(commands: &mut Commands, position: Vec3) -> Entity {
+    commands
+        .spawn()
+        .insert(Station)
+        .insert(Obstacle)
+        .insert(RigidBody::Static)
+        .insert(CollisionShape::Sphere {
+            radius: STATION_RADIUS,
+        })
+        .insert(SpeedZone {
+            radius: STATION_SPEED_ZONE_RADIUS,
+            limit: STATION_SPEED_LIMIT,
+        })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.6, 0.6, 0.65),
+                custom_size: Some(Vec2::splat(STATION_RADIUS * 2.)),
+                ..default()
+            },
+            transform: Transform::from_translation(position),
+            ..default()
+        })
+        .id()
+}
+
+/// A station can be placed inside the world bounds, away from any other collider
+fn is_valid_placement(
+    position: Vec3,
+    obstacle_query: &Query<(&Transform, &CollisionShape), Without<StationGhost>>,
+) -> bool {
+    let in_bounds = position.x.abs() + STATION_RADIUS <= WORLD_HALF_EXTENT
+        && position.y.abs() + STATION_RADIUS <= WORLD_HALF_EXTENT;
+
+    in_bounds
+        && obstacle_query.iter().all(|(transform, shape)| {
+            transform
+                .translation
+                .truncate()
+                .distance(position.truncate())
+                > STATION_RADIUS + bounding_radius(shape)
+        })
+}
+
+/// Radius of a circle enclosing the collision shape
+pub fn bounding_radius(shape: &CollisionShape) -> f32 {
+    match shape {
+        CollisionShape::Sphere { radius } => *radius,
+        CollisionShape::Capsule {
+            half_segment,
+            radius,
+        } => half_segment + radius,
+        CollisionShape::Cuboid {
+            half_extends,
+            border_radius,
+        } => half_extends.truncate().length() + border_radius.unwrap_or(0.),
+        _ => 0.,
+    }
+}
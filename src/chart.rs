@@ -0,0 +1,232 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    debug::DebugDrawConfig,
+    format,
+    localization::Localization,
+    MainCamera, Spaceship,
+};
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+use heron::*;
+use sebaka::steering::SteeringBehaviour;
+use std::collections::VecDeque;
+
+/// Time span shown by the chart, in seconds
+const CHART_WINDOW: f32 = 10.;
+
+/// Size of the chart on screen, in logical pixels
+const CHART_SIZE: Vec2 = Vec2::new(400., 150.);
+
+/// Distance between the chart and the bottom right corner of the window, in logical pixels
+const CHART_MARGIN: f32 = 10.;
+
+#[derive(Clone, Copy)]
+struct ChartSample {
+    time: f32,
+    speed: f32,
+    acceleration: f32,
+    distance: Option<f32>,
+}
+
+/// Scrolling chart of the spaceship speed, acceleration and distance to target. Nothing is recorded while hidden.
+#[derive(Default)]
+pub struct StripChart {
+    pub enabled: bool,
+    samples: VecDeque<ChartSample>,
+}
+
+#[derive(Component)]
+pub struct StripChartLabel;
+
+/// Spawn the scale labels of the chart, above its top right corner
+pub fn spawn_strip_chart_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/DejaVuSans.ttf"),
+        font_size: 14.,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_sections([
+                TextSection::new("", style.clone()),
+                TextSection::new("", style.clone()),
+                TextSection::new("", style),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(CHART_MARGIN),
+                    bottom: Val::Px(CHART_MARGIN + CHART_SIZE.y + 4.),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(StripChartLabel);
+}
+
+/// Show or hide the strip chart with F6, starting over from an empty buffer
+pub fn toggle_strip_chart(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut strip_chart: ResMut<StripChart>,
+) {
+    if bindings.just_pressed(&keys, Action::StripChart) {
+        strip_chart.enabled = !strip_chart.enabled;
+        strip_chart.samples.clear();
+    }
+}
+
+/// Sample the spaceship once the steering acceleration is final
+pub fn record_strip_chart(
+    time: Res<Time>,
+    mut strip_chart: ResMut<StripChart>,
+    query: Query<
+        (
+            &Transform,
+            &Velocity,
+            &Acceleration,
+            Option<&SteeringBehaviour>,
+        ),
+        With<Spaceship>,
+    >,
+    target_query: Query<&Transform>,
+) {
+    if !strip_chart.enabled {
+        return;
+    }
+
+    let (transform, velocity, acceleration, behaviour) = match query.iter().next() {
+        Some(ship) => ship,
+        None => return,
+    };
+    let now = time.seconds_since_startup() as f32;
+    let distance = behaviour
+        .and_then(|behaviour| behaviour.target())
+        .and_then(|target| target_query.get(target).ok())
+        .map(|target| target.translation.distance(transform.translation));
+
+    strip_chart.samples.push_back(ChartSample {
+        time: now,
+        speed: velocity.linear.length(),
+        acceleration: acceleration.linear.length(),
+        distance,
+    });
+    while strip_chart
+        .samples
+        .front()
+        .map_or(false, |sample| now - sample.time > CHART_WINDOW)
+    {
+        strip_chart.samples.pop_front();
+    }
+}
+
+/// Draw the chart in the bottom right corner of the screen, each curve scaled to its own range
+pub fn draw_strip_chart(
+    strip_chart: Res<StripChart>,
+    config: Res<DebugDrawConfig>,
+    localization: Res<Localization>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut lines: ResMut<DebugLines>,
+    mut label_query: Query<(&mut Text, &mut Visibility), With<StripChartLabel>>,
+) {
+    let (mut text, mut visibility) = label_query.single_mut();
+    visibility.is_visible = strip_chart.enabled;
+    if !strip_chart.enabled {
+        return;
+    }
+
+    let (camera, camera_transform) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        _ => return,
+    };
+    // Unknown until the camera is first laid out on its target
+    let viewport_size = match camera.logical_viewport_size() {
+        Some(size) => size,
+        None => return,
+    };
+
+    // Debug lines live in world space, chart corners are brought there through the camera like the cursor
+    let to_world = |screen: Vec2| {
+        crate::screen_to_world(
+            screen,
+            viewport_size,
+            camera_transform,
+            camera.projection_matrix(),
+        )
+    };
+    let origin = Vec2::new(viewport_size.x - CHART_MARGIN - CHART_SIZE.x, CHART_MARGIN);
+
+    let corners = [
+        origin,
+        origin + Vec2::new(CHART_SIZE.x, 0.),
+        origin + CHART_SIZE,
+        origin + Vec2::new(0., CHART_SIZE.y),
+    ];
+    for i in 0..corners.len() {
+        lines.line_colored(
+            to_world(corners[i]),
+            to_world(corners[(i + 1) % corners.len()]),
+            0.,
+            Color::GRAY,
+        );
+    }
+
+    let end_time = match strip_chart.samples.back() {
+        Some(sample) => sample.time,
+        None => return,
+    };
+    let mut plot = |value: fn(&ChartSample) -> Option<f32>, color: Color| {
+        let (min, max) = strip_chart
+            .samples
+            .iter()
+            .filter_map(value)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        let range = (max - min).max(f32::EPSILON);
+
+        let points: Vec<Option<Vec3>> = strip_chart
+            .samples
+            .iter()
+            .map(|sample| {
+                value(sample).map(|v| {
+                    let x = 1. - (end_time - sample.time) / CHART_WINDOW;
+                    let y = (v - min) / range;
+                    to_world(origin + Vec2::new(x, y) * CHART_SIZE)
+                })
+            })
+            .collect();
+        for pair in points.windows(2) {
+            if let [Some(start), Some(end)] = pair {
+                lines.line_colored(*start, *end, 0., color);
+            }
+        }
+
+        (min, max)
+    };
+
+    let speed = plot(|sample| Some(sample.speed), config.velocity_color);
+    let acceleration = plot(
+        |sample| Some(sample.acceleration),
+        config.acceleration_color,
+    );
+    let distance = plot(|sample| sample.distance, config.marker_color);
+
+    let range = |(min, max): (f32, f32), show: &dyn Fn(f32) -> String| {
+        if min <= max {
+            format!("{} – {}   ", show(min), show(max))
+        } else {
+            "—   ".to_string()
+        }
+    };
+    text.sections[0].value = range(speed, &|v| format::format_speed(v, &localization));
+    text.sections[0].style.color = config.velocity_color;
+    text.sections[1].value = range(acceleration, &|v| {
+        format!("{} m/s²", format::format_number(v, &localization))
+    });
+    text.sections[1].style.color = config.acceleration_color;
+    text.sections[2].value = range(distance, &|v| format::format_distance(v, &localization));
+    text.sections[2].style.color = config.marker_color;
+}
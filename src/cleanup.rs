@@ -0,0 +1,308 @@
+use bevy::{ecs::entity::Entities, prelude::*};
+use heron::*;
+use sebaka::steering::SteeringBehaviour;
+
+/// Auxiliary entity (marker, label, anchor...) despawned along with its principal
+#[derive(Component)]
+pub struct DependsOn(pub Entity);
+
+/// Weak entity reference: the entity if it still exists, None otherwise
+pub fn validate(entities: &Entities, entity: Entity) -> Option<Entity> {
+    entities.contains(entity).then_some(entity)
+}
+
+/// Despawn entities whose principal is gone
+pub fn despawn_orphans(
+    mut commands: Commands,
+    entities: &Entities,
+    query: Query<(Entity, &DependsOn)>,
+) {
+    for (entity, depends_on) in &query {
+        if validate(entities, depends_on.0).is_none() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Stop steering ships whose target was despawned, instead of letting them steer toward a stale entity
+///
+/// Interpose losing one of its targets turns into Arrive at the survivor, it is only lost with both.
+pub fn clear_stale_steering_targets(
+    mut commands: Commands,
+    entities: &Entities,
+    mut query: Query<(Entity, &mut SteeringBehaviour, &mut Acceleration)>,
+) {
+    for (entity, mut behaviour, mut acceleration) in &mut query {
+        if let SteeringBehaviour::Interpose {
+            from_target,
+            to_target,
+        } = *behaviour
+        {
+            if let (Some(survivor), None) | (None, Some(survivor)) = (
+                validate(entities, from_target),
+                validate(entities, to_target),
+            ) {
+                info!(
+                    "{:?} lost one target of its Interpose behaviour, arriving at the other",
+                    entity
+                );
+                *behaviour = SteeringBehaviour::Arrive {
+                    target: survivor,
+                    final_angle: None,
+                };
+                continue;
+            }
+        }
+
+        if behaviour
+            .targets()
+            .any(|target| validate(entities, target).is_none())
+        {
+            info!(
+                "{:?} lost the target of its {} behaviour",
+                entity,
+                behaviour.name()
+            );
+            commands.entity(entity).remove::<SteeringBehaviour>();
+            acceleration.linear = Vec3::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Xorshift generator, seeded so that a failing fuzz run replays identically
+    struct Rng(u32);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as usize % n
+        }
+
+        fn pick(&mut self, entities: &[Entity]) -> Entity {
+            entities[self.below(entities.len())]
+        }
+
+        fn position(&mut self) -> Vec3 {
+            Vec3::new(self.below(2000) as f32, self.below(2000) as f32, 0.) - 1000.
+        }
+    }
+
+    /// Stages of the systems touching entity references, in the order they run in the app
+    struct Frame {
+        pre_update: SystemStage,
+        update: SystemStage,
+        post_update: SystemStage,
+    }
+
+    impl Frame {
+        fn new() -> Self {
+            let mut pre_update = SystemStage::single_threaded();
+            pre_update.add_system(clear_stale_steering_targets);
+            let mut update = SystemStage::single_threaded();
+            update.add_system(crate::steering_behaviour);
+            let mut post_update = SystemStage::single_threaded();
+            post_update.add_system(despawn_orphans);
+
+            Self {
+                pre_update,
+                update,
+                post_update,
+            }
+        }
+
+        fn run(&mut self, world: &mut World) {
+            self.pre_update.run(world);
+            self.update.run(world);
+            self.post_update.run(world);
+        }
+    }
+
+    fn spawn_ship(world: &mut World, position: Vec3, velocity: Vec3) -> Entity {
+        world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(
+                Transform::from_translation(position),
+            ))
+            .insert(Velocity::from_linear(velocity))
+            .insert(Acceleration::default())
+            .id()
+    }
+
+    #[test]
+    fn orphans_are_despawned_with_their_children() {
+        let mut world = World::new();
+        let principal = world.spawn().id();
+        let survivor = world.spawn().id();
+        let label = world.spawn().id();
+        let orphan = world
+            .spawn()
+            .insert(DependsOn(principal))
+            .push_children(&[label])
+            .id();
+        let dependent = world.spawn().insert(DependsOn(survivor)).id();
+
+        world.despawn(principal);
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(despawn_orphans);
+        stage.run(&mut world);
+
+        assert!(world.get_entity(orphan).is_none());
+        assert!(world.get_entity(label).is_none());
+        assert!(world.get_entity(dependent).is_some());
+    }
+
+    #[test]
+    fn stale_targets_stop_the_steering() {
+        let mut world = World::new();
+        let gone = world.spawn().id();
+        let target = world.spawn().id();
+        let mut ship = |behaviour: SteeringBehaviour| {
+            world
+                .spawn()
+                .insert(behaviour)
+                .insert(Acceleration::from_linear(Vec3::X * 100.))
+                .id()
+        };
+        let seeking_gone = ship(SteeringBehaviour::Seek { target: gone });
+        let seeking = ship(SteeringBehaviour::Seek { target });
+        let interposing = ship(SteeringBehaviour::Interpose {
+            from_target: gone,
+            to_target: target,
+        });
+        let interposing_gone = ship(SteeringBehaviour::Interpose {
+            from_target: gone,
+            to_target: gone,
+        });
+
+        world.despawn(gone);
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(clear_stale_steering_targets);
+        stage.run(&mut world);
+
+        for lost in [seeking_gone, interposing_gone] {
+            assert!(world.get::<SteeringBehaviour>(lost).is_none());
+            assert_eq!(world.get::<Acceleration>(lost).unwrap().linear, Vec3::ZERO);
+        }
+        assert_eq!(
+            world.get::<SteeringBehaviour>(seeking).unwrap().target(),
+            Some(target)
+        );
+        // Interpose carries on with the surviving target
+        let behaviour = world.get::<SteeringBehaviour>(interposing).unwrap();
+        assert_eq!(behaviour.name(), "Arrive");
+        assert_eq!(behaviour.target(), Some(target));
+    }
+
+    #[test]
+    fn ship_losing_its_target_mid_order_stays_finite_and_idle() {
+        let mut world = World::new();
+        let marker = world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(Transform::from_xyz(
+                0., 1000., 0.,
+            )))
+            .id();
+        let ship = spawn_ship(&mut world, Vec3::ZERO, Vec3::Y * 200.);
+        world.entity_mut(ship).insert(SteeringBehaviour::Arrive {
+            target: marker,
+            final_angle: None,
+        });
+
+        let mut frame = Frame::new();
+        for _ in 0..10 {
+            frame.run(&mut world);
+        }
+        assert_ne!(world.get::<Acceleration>(ship).unwrap().linear, Vec3::ZERO);
+
+        world.despawn(marker);
+        for _ in 0..10 {
+            frame.run(&mut world);
+
+            assert!(world.get::<SteeringBehaviour>(ship).is_none());
+            assert_eq!(world.get::<Acceleration>(ship).unwrap().linear, Vec3::ZERO);
+            assert!(world
+                .get::<Transform>(ship)
+                .unwrap()
+                .translation
+                .is_finite());
+            assert!(world.get::<Velocity>(ship).unwrap().linear.is_finite());
+        }
+    }
+
+    #[test]
+    fn random_despawns_leave_no_orphans_nor_stale_targets() {
+        let mut world = World::new();
+        let mut frame = Frame::new();
+        let mut rng = Rng(0x2545_F491);
+        let mut ships = Vec::new();
+        let mut dependents = Vec::new();
+
+        for _ in 0..1000 {
+            ships.retain(|&ship| world.get_entity(ship).is_some());
+            dependents.retain(|&dependent| world.get_entity(dependent).is_some());
+
+            // Keep a small fleet, each ship carrying auxiliary entities and steering relative to any live entity
+            while ships.len() < 8 {
+                let (position, velocity) = (rng.position(), rng.position() / 10.);
+                ships.push(spawn_ship(&mut world, position, velocity));
+            }
+            let principal = rng.pick(&ships);
+            let position = rng.position();
+            dependents.push(
+                world
+                    .spawn()
+                    .insert_bundle(TransformBundle::from_transform(
+                        Transform::from_translation(position),
+                    ))
+                    .insert(DependsOn(principal))
+                    .id(),
+            );
+            let live: Vec<Entity> = ships.iter().chain(&dependents).copied().collect();
+            let (target, other) = (rng.pick(&live), rng.pick(&live));
+            let behaviour = match rng.below(4) {
+                0 => SteeringBehaviour::Seek { target },
+                1 => SteeringBehaviour::Arrive {
+                    target,
+                    final_angle: None,
+                },
+                2 => SteeringBehaviour::Persue {
+                    target,
+                    min_distance: None,
+                },
+                _ => SteeringBehaviour::Interpose {
+                    from_target: target,
+                    to_target: other,
+                },
+            };
+            world.entity_mut(rng.pick(&ships)).insert(behaviour);
+
+            world.despawn(rng.pick(&live));
+
+            frame.pre_update.run(&mut world);
+            let mut behaviours = world.query::<&SteeringBehaviour>();
+            for behaviour in behaviours.iter(&world) {
+                assert!(behaviour
+                    .targets()
+                    .all(|target| world.get_entity(target).is_some()));
+            }
+
+            frame.update.run(&mut world);
+            let mut accelerations = world.query::<&Acceleration>();
+            assert!(accelerations
+                .iter(&world)
+                .all(|acceleration| acceleration.linear.is_finite()));
+
+            frame.post_update.run(&mut world);
+            let mut principals = world.query::<&DependsOn>();
+            assert!(principals
+                .iter(&world)
+                .all(|depends_on| world.get_entity(depends_on.0).is_some()));
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    build,
+    localization::Localization,
+    MovementMarker, Spaceship,
+};
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+use heron::*;
+use sebaka::steering::{self, SteeringBehaviour};
+use std::f32::consts::TAU;
+
+/// Frame in which velocity debug vectors are drawn
+#[derive(Default, PartialEq, Eq)]
+pub enum DebugFrame {
+    #[default]
+    World,
+    /// Forward and lateral components along the hull
+    Local,
+}
+
+/// Named sets of debug drawing colors
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum DebugDrawPreset {
+    #[default]
+    Default,
+    HighContrast,
+    /// Okabe-Ito colors, telling apart vectors without relying on red and green
+    DeuteranopiaFriendly,
+}
+
+impl DebugDrawPreset {
+    pub fn next(self) -> Self {
+        match self {
+            DebugDrawPreset::Default => DebugDrawPreset::HighContrast,
+            DebugDrawPreset::HighContrast => DebugDrawPreset::DeuteranopiaFriendly,
+            DebugDrawPreset::DeuteranopiaFriendly => DebugDrawPreset::Default,
+        }
+    }
+}
+
+/// How debug vectors are tinted when several ships are on screen
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum DebugColorMode {
+    /// Preset colors, the same for every entity
+    #[default]
+    Fixed,
+    /// Stable hue derived from the entity id
+    Entity,
+    /// Hue of the current steering behaviour variant
+    Behaviour,
+}
+
+impl DebugColorMode {
+    pub fn next(self) -> Self {
+        match self {
+            DebugColorMode::Fixed => DebugColorMode::Entity,
+            DebugColorMode::Entity => DebugColorMode::Behaviour,
+            DebugColorMode::Behaviour => DebugColorMode::Fixed,
+        }
+    }
+}
+
+/// Colors and sizes used by every debug drawing system
+pub struct DebugDrawConfig {
+    pub preset: DebugDrawPreset,
+    pub color_mode: DebugColorMode,
+    pub velocity_color: Color,
+    pub lateral_velocity_color: Color,
+    pub acceleration_color: Color,
+    pub marker_color: Color,
+    pub order_preview_color: Color,
+    pub zone_color: Color,
+    /// Ring confirming a move order
+    pub ping_color: Color,
+    /// Number of segments of circles, finer circles costing more lines
+    pub circle_segments: usize,
+    /// Length of velocity vectors per unit of speed
+    pub velocity_scale: f32,
+    /// Length of acceleration vectors per unit of acceleration
+    pub acceleration_scale: f32,
+    /// Half size of crosshairs
+    pub crosshair_size: f32,
+}
+
+impl DebugDrawConfig {
+    pub fn from_preset(preset: DebugDrawPreset) -> Self {
+        let mut config = Self {
+            preset,
+            color_mode: DebugColorMode::default(),
+            velocity_color: Color::NONE,
+            lateral_velocity_color: Color::NONE,
+            acceleration_color: Color::NONE,
+            marker_color: Color::NONE,
+            order_preview_color: Color::NONE,
+            zone_color: Color::NONE,
+            ping_color: Color::NONE,
+            circle_segments: 64,
+            velocity_scale: 1.,
+            acceleration_scale: 1.,
+            crosshair_size: 10.,
+        };
+        config.set_preset(preset);
+        config
+    }
+
+    /// Switch to the colors of `preset`, keeping the color mode and sizes as they are
+    pub fn set_preset(&mut self, preset: DebugDrawPreset) {
+        let (velocity, lateral_velocity, acceleration, marker, order_preview, zone, ping) =
+            match preset {
+                DebugDrawPreset::Default => (
+                    Color::YELLOW,
+                    Color::ORANGE,
+                    Color::BLUE,
+                    Color::RED,
+                    Color::GREEN,
+                    Color::GRAY,
+                    Color::rgb(0.2, 1.0, 0.3),
+                ),
+                DebugDrawPreset::HighContrast => (
+                    Color::WHITE,
+                    Color::FUCHSIA,
+                    Color::CYAN,
+                    Color::YELLOW,
+                    Color::LIME_GREEN,
+                    Color::SILVER,
+                    Color::LIME_GREEN,
+                ),
+                DebugDrawPreset::DeuteranopiaFriendly => (
+                    Color::rgb(0.902, 0.624, 0.0),
+                    Color::rgb(0.8, 0.475, 0.655),
+                    Color::rgb(0.337, 0.706, 0.914),
+                    Color::rgb(0.941, 0.894, 0.259),
+                    Color::rgb(0.0, 0.447, 0.698),
+                    Color::rgb(0.0, 0.620, 0.451),
+                    Color::rgb(0.835, 0.369, 0.0),
+                ),
+            };
+
+        self.preset = preset;
+        self.velocity_color = velocity;
+        self.lateral_velocity_color = lateral_velocity;
+        self.acceleration_color = acceleration;
+        self.marker_color = marker;
+        self.order_preview_color = order_preview;
+        self.zone_color = zone;
+        self.ping_color = ping;
+    }
+}
+
+impl DebugDrawConfig {
+    /// Color of an entity's debug vector, `fixed` being the preset color and `lightness` telling vector kinds apart in tinted modes
+    pub fn tint(
+        &self,
+        entity: Entity,
+        behaviour: Option<&SteeringBehaviour>,
+        fixed: Color,
+        lightness: f32,
+    ) -> Color {
+        let hue = match (self.color_mode, behaviour) {
+            (DebugColorMode::Fixed, _) => return fixed,
+            // Golden ratio spacing keeps consecutive ids far apart on the color wheel
+            (DebugColorMode::Entity, _) => (entity.id() as f32 * 0.618_034).fract() * 360.,
+            (DebugColorMode::Behaviour, Some(behaviour)) => behaviour_hue(behaviour),
+            (DebugColorMode::Behaviour, None) => return Color::hsl(0., 0., lightness),
+        };
+
+        Color::hsl(hue, 0.9, lightness)
+    }
+}
+
+/// Hue given to each steering behaviour variant
+fn behaviour_hue(behaviour: &SteeringBehaviour) -> f32 {
+    match behaviour {
+        SteeringBehaviour::Seek { .. } => 120.,
+        SteeringBehaviour::Arrive { .. } => 180.,
+        SteeringBehaviour::Persue { .. } => 30.,
+        SteeringBehaviour::Flee { .. } => 0.,
+        SteeringBehaviour::Evade { .. } => 300.,
+        SteeringBehaviour::FollowPath { .. } => 60.,
+        SteeringBehaviour::Interpose { .. } => 220.,
+        SteeringBehaviour::Hide { .. } => 270.,
+        SteeringBehaviour::OffsetPursuit { .. } => 330.,
+    }
+}
+
+impl Default for DebugDrawConfig {
+    fn default() -> Self {
+        Self::from_preset(DebugDrawPreset::default())
+    }
+}
+
+#[derive(Component)]
+pub struct SlipAngleLabel;
+
+/// Spawn the slip angle readout, shown with hull frame debug vectors
+pub fn spawn_slip_angle_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 16.,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(SlipAngleLabel);
+}
+
+/// Switch velocity debug vectors between world and hull frames with F3
+pub fn toggle_debug_frame(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut frame: ResMut<DebugFrame>,
+) {
+    if bindings.just_pressed(&keys, Action::DebugFrame) {
+        *frame = match *frame {
+            DebugFrame::World => DebugFrame::Local,
+            DebugFrame::Local => DebugFrame::World,
+        };
+    }
+}
+
+/// Cycle debug drawing presets with F4, applying immediately without resetting the color mode
+pub fn cycle_debug_draw_preset(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut config: ResMut<DebugDrawConfig>,
+) {
+    if bindings.just_pressed(&keys, Action::DebugDrawPreset) {
+        let preset = config.preset.next();
+        config.set_preset(preset);
+        info!("Debug draw preset: {:?}", config.preset);
+    }
+}
+
+/// Cycle how debug vectors are tinted with F2: preset colors, per entity or per behaviour
+pub fn cycle_debug_color_mode(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut config: ResMut<DebugDrawConfig>,
+) {
+    if bindings.just_pressed(&keys, Action::DebugColorMode) {
+        config.color_mode = config.color_mode.next();
+        info!("Debug color mode: {:?}", config.color_mode);
+    }
+}
+
+pub fn debug_velocity(
+    query: Query<(Entity, &Transform, &Velocity, Option<&SteeringBehaviour>)>,
+    frame: Res<DebugFrame>,
+    config: Res<DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for (entity, transform, velocity, behaviour) in &query {
+        let start = transform.translation;
+        let velocity_color = config.tint(entity, behaviour, config.velocity_color, 0.6);
+        match *frame {
+            DebugFrame::World => {
+                let end = start + velocity.linear * config.velocity_scale;
+                lines.line_colored(start, end, 0., velocity_color);
+            }
+            DebugFrame::Local => {
+                // Forward along the nose, lateral perpendicular to it
+                let local = steering::to_hull_frame(transform.rotation, velocity.linear)
+                    * config.velocity_scale;
+                let forward = transform.rotation * Vec3::Y * local.y;
+                let lateral = transform.rotation * Vec3::X * local.x;
+                let lateral_color =
+                    config.tint(entity, behaviour, config.lateral_velocity_color, 0.8);
+                lines.line_colored(start, start + forward, 0., velocity_color);
+                lines.line_colored(start, start + lateral, 0., lateral_color);
+            }
+        }
+    }
+}
+
+/// Display the slip angle of the spaceship while debug vectors are in hull frame
+pub fn debug_slip_angle(
+    frame: Res<DebugFrame>,
+    localization: Res<Localization>,
+    query: Query<(&Transform, &Velocity), With<Spaceship>>,
+    mut label_query: Query<(&mut Text, &mut Visibility), With<SlipAngleLabel>>,
+) {
+    let (mut text, mut visibility) = label_query.single_mut();
+    visibility.is_visible = *frame == DebugFrame::Local;
+
+    if let Some((transform, velocity)) = query.iter().next() {
+        let slip_angle = steering::slip_angle(transform.rotation, velocity.linear);
+        text.sections[0].value = format!(
+            "{} {:+04.0}°",
+            localization.get("hud.slip"),
+            slip_angle.to_degrees()
+        );
+    }
+}
+
+pub fn debug_acceleration(
+    query: Query<(
+        Entity,
+        &Transform,
+        &Acceleration,
+        Option<&SteeringBehaviour>,
+    )>,
+    config: Res<DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for (entity, transform, acceleration, behaviour) in &query {
+        let start = transform.translation;
+        let end = start + acceleration.linear * config.acceleration_scale;
+        let color = config.tint(entity, behaviour, config.acceleration_color, 0.35);
+        lines.line_colored(start, start + (start - end), 0., color);
+    }
+}
+
+/// Draw a line from each steering entity to its current target, in the entity's tint
+pub fn debug_steering_target(
+    query: Query<(Entity, &Transform, &SteeringBehaviour)>,
+    target_query: Query<&Transform>,
+    config: Res<DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for (entity, transform, behaviour) in &query {
+        let target_transform = match behaviour.target().map(|target| target_query.get(target)) {
+            Some(Ok(target_transform)) => target_transform,
+            _ => continue,
+        };
+
+        let color = config.tint(entity, Some(behaviour), config.marker_color, 0.6);
+        lines.line_colored(
+            transform.translation,
+            target_transform.translation,
+            0.,
+            color,
+        );
+    }
+}
+
+/// Draw the remaining waypoints of entities following a path, with the radius at which each one counts as reached
+pub fn debug_path(
+    query: Query<(Entity, &Transform, &SteeringBehaviour)>,
+    config: Res<DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for (entity, transform, behaviour) in &query {
+        if let SteeringBehaviour::FollowPath {
+            path,
+            current_index,
+        } = behaviour
+        {
+            let color = config.tint(entity, Some(behaviour), config.marker_color, 0.6);
+            let mut previous = transform.translation;
+            for &waypoint in path.iter().skip(*current_index) {
+                lines.line_colored(previous, waypoint, 0., color);
+                circle(
+                    &mut lines,
+                    config.circle_segments,
+                    waypoint,
+                    steering::PATH_ARRIVAL_RADIUS,
+                    color,
+                );
+                previous = waypoint;
+            }
+        }
+    }
+}
+
+/// Draw the spot chosen by hiding entities, to inspect which obstacle they picked
+pub fn debug_hiding_spots(
+    query: Query<(Entity, &Transform, &SteeringBehaviour)>,
+    target_query: Query<&Transform>,
+    obstacle_query: Query<(&Transform, &CollisionShape), With<steering::Obstacle>>,
+    config: Res<DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for (entity, transform, behaviour) in &query {
+        let threat = match behaviour {
+            SteeringBehaviour::Hide { target } => match target_query.get(*target) {
+                Ok(threat) => threat,
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+
+        let obstacles = obstacle_query
+            .iter()
+            .map(|(transform, shape)| (transform.translation, build::bounding_radius(shape)));
+        if let Some(spot) =
+            steering::hiding_spot(transform.translation, threat.translation, obstacles)
+        {
+            let color = config.tint(entity, Some(behaviour), config.marker_color, 0.6);
+            circle(
+                &mut lines,
+                config.circle_segments,
+                spot,
+                config.crosshair_size * 2.,
+                color,
+            );
+            lines.line_colored(threat.translation, spot, 0., color);
+        }
+    }
+}
+
+/// Draw a crosshair on MovementMarker position
+pub fn debug_movement_marker(
+    target_query: Query<&Transform, With<MovementMarker>>,
+    config: Res<DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    let target_tranform = target_query.single();
+    lines.line_colored(
+        target_tranform.translation + Vec3::NEG_X * config.crosshair_size,
+        target_tranform.translation + Vec3::X * config.crosshair_size,
+        0.,
+        config.marker_color,
+    );
+    lines.line_colored(
+        target_tranform.translation + Vec3::NEG_Y * config.crosshair_size,
+        target_tranform.translation + Vec3::Y * config.crosshair_size,
+        0.,
+        config.marker_color,
+    );
+}
+
+/// Draw a circle in the XY plane, made of `segments` lines
+pub fn circle(lines: &mut DebugLines, segments: usize, center: Vec3, radius: f32, color: Color) {
+    let point = |i: usize| {
+        let angle = i as f32 / segments as f32 * TAU;
+        center + Vec3::new(angle.cos(), angle.sin(), 0.) * radius
+    };
+    for i in 0..segments {
+        lines.line_colored(point(i), point(i + 1), 0., color);
+    }
+}
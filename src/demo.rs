@@ -0,0 +1,238 @@
+use crate::{
+    avoidance::ObstacleAvoidance,
+    build,
+    flocking::{Alignment, Cohesion, Separation},
+    MaxAcceleration, MaxJerk, MaxVelocity, MovementMarker, Spaceship,
+};
+use bevy::prelude::*;
+use heron::*;
+use sebaka::steering::SteeringBehaviour;
+
+/// Center of the demo scene, away from the spaceship start
+const DEMO_CENTER: Vec3 = Vec3::new(4_000., 0., 0.);
+
+/// Center of the flocking demo, on the other side of the spaceship start
+const FLOCK_CENTER: Vec3 = Vec3::new(-6_000., 0., 0.);
+
+/// Number of boids in the flocking demo
+const BOID_COUNT: usize = 50;
+
+/// Dummy circling around a center at constant speed, a moving target for the steering demos
+#[derive(Component)]
+pub struct Orbit {
+    pub center: Vec3,
+    pub radius: f32,
+    pub speed: f32,
+}
+
+/// Spawn dummy targets and the ships steering relative to them, with --demo
+///
+/// Runs after startup, so that the spaceship exists to be escorted.
+pub fn spawn_demo(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ship_query: Query<Entity, With<Spaceship>>,
+    marker_query: Query<Entity, With<MovementMarker>>,
+) {
+    // Wingmen escorting the spaceship, fast enough to keep up with it
+    for ship in &ship_query {
+        for offset in [Vec3::new(-300., -300., 0.), Vec3::new(300., -300., 0.)] {
+            let wingman = spawn_drone(
+                &mut commands,
+                &asset_server,
+                offset,
+                SteeringBehaviour::OffsetPursuit {
+                    leader: ship,
+                    offset,
+                },
+            );
+            commands.entity(wingman).insert(MaxVelocity(1_500.));
+        }
+    }
+
+    // A group following the move orders too, spreading around the marker instead of stacking on it
+    for marker in &marker_query {
+        for i in 0..10 {
+            let drone = spawn_drone(
+                &mut commands,
+                &asset_server,
+                Vec3::new(-2_000. + i as f32 * 120., -1_500., 0.),
+                SteeringBehaviour::Arrive {
+                    target: marker,
+                    final_angle: None,
+                },
+            );
+            commands.entity(drone).insert(Separation {
+                radius: 250.,
+                weight: 1.,
+            });
+        }
+    }
+
+    let dummy = spawn_dummy(
+        &mut commands,
+        &asset_server,
+        Orbit {
+            center: DEMO_CENTER,
+            radius: 1_500.,
+            speed: 300.,
+        },
+    );
+
+    // Stays behind the station at the center of the dummy orbit
+    build::spawn_station(&mut commands, DEMO_CENTER);
+    spawn_drone(
+        &mut commands,
+        &asset_server,
+        DEMO_CENTER + Vec3::new(-1_000., 0., 0.),
+        SteeringBehaviour::Hide { target: dummy },
+    );
+
+    // Leads the dummy and cuts across the circle rather than tail-chasing it
+    spawn_drone(
+        &mut commands,
+        &asset_server,
+        DEMO_CENTER + Vec3::new(0., -2_500., 0.),
+        SteeringBehaviour::Persue {
+            target: dummy,
+            min_distance: Some(300.),
+        },
+    );
+
+    // Goes around a square once and stops on its last corner, ramping its thrust at corners instead of flipping it
+    let path_drone = spawn_drone(
+        &mut commands,
+        &asset_server,
+        DEMO_CENTER + Vec3::new(-2_500., 2_500., 0.),
+        SteeringBehaviour::FollowPath {
+            path: vec![
+                DEMO_CENTER + Vec3::new(-2_000., 2_000., 0.),
+                DEMO_CENTER + Vec3::new(2_000., 2_000., 0.),
+                DEMO_CENTER + Vec3::new(2_000., -2_000., 0.),
+                DEMO_CENTER + Vec3::new(-2_000., -2_000., 0.),
+                DEMO_CENTER + Vec3::new(-2_000., 1_000., 0.),
+            ],
+            current_index: 0,
+        },
+    );
+    commands.entity(path_drone).insert(MaxJerk(400.));
+
+    // Two dummies on circles of different speeds, the gap between them keeps opening and closing
+    let left = spawn_dummy(
+        &mut commands,
+        &asset_server,
+        Orbit {
+            center: DEMO_CENTER + Vec3::new(-1_500., 5_000., 0.),
+            radius: 800.,
+            speed: 250.,
+        },
+    );
+    let right = spawn_dummy(
+        &mut commands,
+        &asset_server,
+        Orbit {
+            center: DEMO_CENTER + Vec3::new(1_500., 5_000., 0.),
+            radius: 800.,
+            speed: 400.,
+        },
+    );
+    spawn_drone(
+        &mut commands,
+        &asset_server,
+        DEMO_CENTER + Vec3::new(0., 3_500., 0.),
+        SteeringBehaviour::Interpose {
+            from_target: left,
+            to_target: right,
+        },
+    );
+}
+
+/// Spawn a flock of boids with all three group behaviours, loosely persuing a dummy so that they stay in view
+pub fn spawn_boids(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let leader = spawn_dummy(
+        &mut commands,
+        &asset_server,
+        Orbit {
+            center: FLOCK_CENTER,
+            radius: 2_500.,
+            speed: 250.,
+        },
+    );
+
+    for i in 0..BOID_COUNT {
+        // Sunflower spiral, evenly spread without any randomness
+        let angle = i as f32 * 2.399_963;
+        let distance = 80. * (i as f32).sqrt();
+        let boid = spawn_drone(
+            &mut commands,
+            &asset_server,
+            FLOCK_CENTER + Vec3::new(angle.cos(), angle.sin(), 0.) * distance,
+            SteeringBehaviour::Persue {
+                target: leader,
+                min_distance: Some(800.),
+            },
+        );
+        commands
+            .entity(boid)
+            .insert(Separation {
+                radius: 200.,
+                weight: 1.5,
+            })
+            .insert(Cohesion {
+                radius: 600.,
+                weight: 0.5,
+            })
+            .insert(Alignment {
+                radius: 400.,
+                weight: 0.5,
+            });
+    }
+}
+
+fn spawn_dummy(commands: &mut Commands, asset_server: &AssetServer, orbit: Orbit) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: asset_server.load("asteroid.png"),
+            transform: Transform::from_translation(orbit.center + Vec3::X * orbit.radius),
+            ..default()
+        })
+        .insert(RigidBody::KinematicVelocityBased)
+        .insert(CollisionShape::Sphere { radius: 60. })
+        .insert(Velocity::from_linear(Vec3::ZERO))
+        .insert(orbit)
+        .id()
+}
+
+fn spawn_drone(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec3,
+    behaviour: SteeringBehaviour,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: asset_server.load("ship2.png"),
+            transform: Transform::from_translation(position),
+            ..default()
+        })
+        .insert(RigidBody::Dynamic)
+        .insert(CollisionShape::Sphere { radius: 50. })
+        .insert(Velocity::from_linear(Vec3::ZERO))
+        .insert(Acceleration::from_linear(Vec3::ZERO))
+        .insert(MaxVelocity(600.))
+        .insert(MaxAcceleration(200.))
+        .insert(ObstacleAvoidance::default())
+        .insert(behaviour)
+        .id()
+}
+
+/// Keep orbiting dummies on their circle
+pub fn orbit(mut query: Query<(&Transform, &mut Velocity, &Orbit)>) {
+    for (transform, mut velocity, orbit) in &mut query {
+        let offset = transform.translation - orbit.center;
+        let tangent = Vec3::new(-offset.y, offset.x, 0.).normalize_or_zero();
+        // Pull back toward the circle, integration errors would otherwise make it spiral outward
+        let correction = offset.normalize_or_zero() * (orbit.radius - offset.length());
+        velocity.linear = tangent * orbit.speed + correction;
+    }
+}
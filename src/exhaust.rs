@@ -0,0 +1,207 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    Spaceship, ThrusterEffect,
+};
+use bevy::prelude::*;
+use bevy_hanabi::*;
+use std::collections::HashMap;
+
+/// Look of the exhaust of a ship's thrusters
+#[derive(Component, Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub enum ExhaustProfile {
+    /// Orange flame fading into grey smoke
+    #[default]
+    Chemical,
+    /// Thin blue plume without smoke
+    Ion,
+    /// Sputtering flame and thick dark smoke
+    Damaged,
+    /// Larger, white hot flame
+    Boost,
+}
+
+impl ExhaustProfile {
+    pub const ALL: [ExhaustProfile; 4] = [
+        ExhaustProfile::Chemical,
+        ExhaustProfile::Ion,
+        ExhaustProfile::Damaged,
+        ExhaustProfile::Boost,
+    ];
+
+    pub fn next(self) -> Self {
+        match self {
+            ExhaustProfile::Chemical => ExhaustProfile::Ion,
+            ExhaustProfile::Ion => ExhaustProfile::Damaged,
+            ExhaustProfile::Damaged => ExhaustProfile::Boost,
+            ExhaustProfile::Boost => ExhaustProfile::Chemical,
+        }
+    }
+
+    fn size_gradient(self) -> Gradient<Vec2> {
+        let mut gradient = Gradient::new();
+        match self {
+            ExhaustProfile::Chemical => {
+                gradient.add_key(0.00, Vec2::splat(6.8));
+                gradient.add_key(0.05, Vec2::splat(4.5));
+                gradient.add_key(0.10, Vec2::splat(1.2));
+                gradient.add_key(0.15, Vec2::splat(0.2));
+                gradient.add_key(0.25, Vec2::splat(8.5));
+                gradient.add_key(1.00, Vec2::splat(0.5));
+            }
+            ExhaustProfile::Ion => {
+                gradient.add_key(0.00, Vec2::splat(4.0));
+                gradient.add_key(0.10, Vec2::splat(2.5));
+                gradient.add_key(0.30, Vec2::splat(0.5));
+                gradient.add_key(1.00, Vec2::splat(0.0));
+            }
+            ExhaustProfile::Damaged => {
+                gradient.add_key(0.00, Vec2::splat(5.0));
+                gradient.add_key(0.05, Vec2::splat(2.0));
+                gradient.add_key(0.10, Vec2::splat(6.0));
+                gradient.add_key(0.40, Vec2::splat(14.0));
+                gradient.add_key(1.00, Vec2::splat(18.0));
+            }
+            ExhaustProfile::Boost => {
+                gradient.add_key(0.00, Vec2::splat(10.0));
+                gradient.add_key(0.10, Vec2::splat(7.0));
+                gradient.add_key(0.20, Vec2::splat(2.0));
+                gradient.add_key(0.30, Vec2::splat(9.0));
+                gradient.add_key(1.00, Vec2::splat(0.5));
+            }
+        }
+        gradient
+    }
+
+    fn color_gradient(self) -> Gradient<Vec4> {
+        let mut gradient = Gradient::new();
+        match self {
+            ExhaustProfile::Chemical => {
+                gradient.add_key(0.00, Vec4::new(1.0, 0.8, 0.3, 1.0));
+                gradient.add_key(0.03, Vec4::new(1.0, 0.66, 0.0, 1.0));
+                gradient.add_key(0.10, Vec4::new(1.0, 0.55, 0.0, 0.8));
+                gradient.add_key(0.15, Vec4::new(0.0, 0.0, 0.0, 0.0));
+                gradient.add_key(0.25, Vec4::new(0.56, 0.52, 0.51, 0.8));
+                gradient.add_key(1.00, Vec4::new(0.56, 0.52, 0.51, 0.0));
+            }
+            ExhaustProfile::Ion => {
+                gradient.add_key(0.00, Vec4::new(0.8, 0.95, 1.0, 1.0));
+                gradient.add_key(0.05, Vec4::new(0.3, 0.6, 1.0, 1.0));
+                gradient.add_key(0.30, Vec4::new(0.1, 0.3, 0.9, 0.5));
+                gradient.add_key(1.00, Vec4::new(0.05, 0.1, 0.6, 0.0));
+            }
+            ExhaustProfile::Damaged => {
+                gradient.add_key(0.00, Vec4::new(1.0, 0.5, 0.1, 0.8));
+                gradient.add_key(0.05, Vec4::new(0.6, 0.2, 0.0, 0.6));
+                gradient.add_key(0.10, Vec4::new(0.2, 0.18, 0.17, 0.9));
+                gradient.add_key(1.00, Vec4::new(0.1, 0.1, 0.1, 0.0));
+            }
+            ExhaustProfile::Boost => {
+                gradient.add_key(0.00, Vec4::new(1.0, 1.0, 0.9, 1.0));
+                gradient.add_key(0.05, Vec4::new(1.0, 0.9, 0.5, 1.0));
+                gradient.add_key(0.15, Vec4::new(1.0, 0.6, 0.1, 0.9));
+                gradient.add_key(0.20, Vec4::new(0.0, 0.0, 0.0, 0.0));
+                gradient.add_key(0.30, Vec4::new(0.56, 0.52, 0.51, 0.6));
+                gradient.add_key(1.00, Vec4::new(0.56, 0.52, 0.51, 0.0));
+            }
+        }
+        gradient
+    }
+}
+
+/// Thruster sizes, each needing its own emitter shape
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Nozzle {
+    Main,
+    Maneuvering,
+}
+
+impl Nozzle {
+    const ALL: [Nozzle; 2] = [Nozzle::Main, Nozzle::Maneuvering];
+
+    fn base_radius(self) -> f32 {
+        match self {
+            Nozzle::Main => 25.,
+            Nozzle::Maneuvering => 5.,
+        }
+    }
+}
+
+/// Effect assets of every exhaust profile and nozzle, created once and shared by all thrusters.
+/// Gradients are baked into the assets, so switching profile swaps handles rather than building new assets.
+pub struct ExhaustEffects {
+    handles: HashMap<(ExhaustProfile, Nozzle), Handle<EffectAsset>>,
+}
+
+impl ExhaustEffects {
+    pub fn new(effects: &mut Assets<EffectAsset>) -> Self {
+        let mut handles = HashMap::new();
+        for profile in ExhaustProfile::ALL {
+            for nozzle in Nozzle::ALL {
+                handles.insert(
+                    (profile, nozzle),
+                    effects.add(effect_asset(profile, nozzle)),
+                );
+            }
+        }
+
+        Self { handles }
+    }
+
+    pub fn get(&self, profile: ExhaustProfile, nozzle: Nozzle) -> Handle<EffectAsset> {
+        self.handles[&(profile, nozzle)].clone()
+    }
+}
+
+fn effect_asset(profile: ExhaustProfile, nozzle: Nozzle) -> EffectAsset {
+    EffectAsset {
+        name: "thruster".into(),
+        capacity: 32768,
+        spawner: Spawner::rate(1000.0.into()),
+        ..Default::default()
+    }
+    .init(PositionCone3dModifier {
+        speed: 250.0.into(),
+        dimension: ShapeDimension::Volume,
+        base_radius: nozzle.base_radius(),
+        top_radius: 1.,
+        height: 50.,
+    })
+    .init(ParticleLifetimeModifier { lifetime: 1.5 })
+    .render(SizeOverLifetimeModifier {
+        gradient: profile.size_gradient(),
+    })
+    .render(ColorOverLifetimeModifier {
+        gradient: profile.color_gradient(),
+    })
+}
+
+/// Cycle the exhaust profile of the spaceship with F7
+pub fn cycle_exhaust_profile(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut query: Query<&mut ExhaustProfile, With<Spaceship>>,
+) {
+    if bindings.just_pressed(&keys, Action::ExhaustProfile) {
+        for mut profile in &mut query {
+            *profile = profile.next();
+            info!("Exhaust profile: {:?}", *profile);
+        }
+    }
+}
+
+/// Swap the effect of thrusters when the exhaust profile of their ship changes
+pub fn apply_exhaust_profile(
+    exhaust_effects: Res<ExhaustEffects>,
+    query: Query<(&ExhaustProfile, &Children), Changed<ExhaustProfile>>,
+    mut thruster_query: Query<(&mut ParticleEffect, &ThrusterEffect)>,
+) {
+    for (profile, children) in &query {
+        for &child in children {
+            if let Ok((mut effect, thruster)) = thruster_query.get_mut(child) {
+                // The spawner rate is set every frame by thruster_power, nothing else needs carrying over
+                *effect = ParticleEffect::new(exhaust_effects.get(*profile, thruster.nozzle))
+                    .with_z_layer_2d(Some(0.1));
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    format,
+    localization::Localization,
+    stance::HoldPosition,
+    zones::SpeedCap,
+    MaxAcceleration, Spaceship,
+};
+use bevy::prelude::*;
+use heron::*;
+use sebaka::steering;
+
+/// Ship flown with the keyboard instead of its steering behaviour
+#[derive(Component)]
+pub struct ManualControl;
+
+/// Counter-thrust cancelling drift on axes without manual input
+#[derive(Component)]
+pub struct FlightAssist;
+
+/// Ship system damping velocity for arcade-like handling, powered at the cost of part of the thrust
+#[derive(Component)]
+pub struct InertiaDampener {
+    pub enabled: bool,
+    /// Linear damping applied while powered
+    pub damping: f32,
+    /// Fraction of the thrust diverted to the dampener while powered
+    pub power_draw: f32,
+}
+
+impl Default for InertiaDampener {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            damping: 0.8,
+            power_draw: 0.3,
+        }
+    }
+}
+
+/// Acceleration left for propulsion once the inertia dampener, if any, took its share
+pub fn available_thrust(max_acceleration: f32, dampener: Option<&InertiaDampener>) -> f32 {
+    match dampener {
+        Some(dampener) if dampener.enabled => max_acceleration * (1. - dampener.power_draw),
+        _ => max_acceleration,
+    }
+}
+
+#[derive(Component)]
+pub struct FlightModeLabel;
+
+pub fn spawn_flight_mode_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 16.,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(FlightModeLabel);
+}
+
+/// Toggle manual flight with M and flight assist with X on the spaceship
+pub fn toggle_flight_modes(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    query: Query<(Entity, Option<&ManualControl>, Option<&FlightAssist>), With<Spaceship>>,
+) {
+    for (entity, manual_control, flight_assist) in &query {
+        if bindings.just_pressed(&keys, Action::ManualFlight) {
+            if manual_control.is_some() {
+                commands.entity(entity).remove::<ManualControl>();
+            } else {
+                commands.entity(entity).insert(ManualControl);
+            }
+        }
+
+        if bindings.just_pressed(&keys, Action::FlightAssist) {
+            if flight_assist.is_some() {
+                commands.entity(entity).remove::<FlightAssist>();
+            } else {
+                commands.entity(entity).insert(FlightAssist);
+            }
+        }
+    }
+}
+
+/// Power the inertia dampener of the spaceship on or off with Z
+pub fn toggle_inertia_dampener(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut query: Query<&mut InertiaDampener, With<Spaceship>>,
+) {
+    if bindings.just_pressed(&keys, Action::InertiaDampener) {
+        for mut dampener in &mut query {
+            dampener.enabled = !dampener.enabled;
+        }
+    }
+}
+
+/// Apply the physics damping of inertia dampeners when they are toggled
+pub fn apply_inertia_dampener(
+    mut commands: Commands,
+    query: Query<(Entity, &InertiaDampener), Changed<InertiaDampener>>,
+) {
+    for (entity, dampener) in &query {
+        let damping = if dampener.enabled {
+            dampener.damping
+        } else {
+            0.
+        };
+        commands
+            .entity(entity)
+            .insert(Damping::from_linear(damping));
+    }
+}
+
+/// Update acceleration of manually controlled ships from WASD, in world axes
+pub fn manual_flight(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut query: Query<
+        (
+            &Velocity,
+            &mut Acceleration,
+            Option<&MaxAcceleration>,
+            Option<&FlightAssist>,
+            Option<&InertiaDampener>,
+        ),
+        With<ManualControl>,
+    >,
+) {
+    let axis = |negative, positive| {
+        (bindings.pressed(&keys, positive) as i8 - bindings.pressed(&keys, negative) as i8) as f32
+    };
+    let input = Vec2::new(
+        axis(Action::ThrustLeft, Action::ThrustRight),
+        axis(Action::ThrustBackward, Action::ThrustForward),
+    );
+
+    for (velocity, mut acceleration, max_acceleration, flight_assist, dampener) in &mut query {
+        let max_acceleration =
+            available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+        acceleration.linear = steering::manual_thrust(
+            input,
+            velocity.linear,
+            flight_assist.is_some(),
+            max_acceleration,
+            time.delta_seconds(),
+        );
+    }
+}
+
+/// Show the flight mode of the spaceship, and whether a speed zone limits it
+pub fn flight_mode_label(
+    localization: Res<Localization>,
+    query: Query<
+        (
+            &Velocity,
+            Option<&ManualControl>,
+            Option<&FlightAssist>,
+            Option<&SpeedCap>,
+            Option<&HoldPosition>,
+            Option<&InertiaDampener>,
+        ),
+        With<Spaceship>,
+    >,
+    mut label_query: Query<&mut Text, With<FlightModeLabel>>,
+) {
+    let mut text = label_query.single_mut();
+
+    if let Some((velocity, manual_control, flight_assist, speed_cap, hold, dampener)) =
+        query.iter().next()
+    {
+        text.sections[0].value = format!(
+            "{} · {} · {} {}{}{}",
+            localization.get(match (manual_control, hold) {
+                (Some(_), _) => "hud.manual",
+                (None, Some(_)) => "hud.hold",
+                (None, None) => "hud.autopilot",
+            }),
+            format::format_speed(velocity.linear.length(), &localization),
+            localization.get("hud.assist"),
+            localization.get(if flight_assist.is_some() {
+                "hud.on"
+            } else {
+                "hud.off"
+            }),
+            match dampener {
+                Some(dampener) => format!(
+                    " · {} {}",
+                    localization.get("hud.dampener"),
+                    localization.get(if dampener.enabled {
+                        "hud.on"
+                    } else {
+                        "hud.off"
+                    })
+                ),
+                None => String::new(),
+            },
+            match speed_cap {
+                Some(cap) if cap.inside => format!(" · {}", localization.get("hud.speed_limited")),
+                _ => String::new(),
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use steering::{Integrator, Kinematics};
+
+    #[test]
+    fn powered_dampener_takes_its_share_of_the_thrust() {
+        let powered = InertiaDampener {
+            enabled: true,
+            ..default()
+        };
+
+        assert_eq!(available_thrust(100., None), 100.);
+        assert_eq!(
+            available_thrust(100., Some(&InertiaDampener::default())),
+            100.
+        );
+        assert!((available_thrust(100., Some(&powered)) - 70.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn arrive_converges_on_the_thrust_left_by_the_dampener() {
+        // Only the thrust budget is modelled: heron's linear Damping, applied by the physics step while the
+        // dampener is powered, is not simulated here
+        let powered = InertiaDampener {
+            enabled: true,
+            ..default()
+        };
+        for dampener in [None, Some(&powered)] {
+            let max_acceleration = available_thrust(100., dampener);
+            let target = Vec3::Y * 2000.;
+            let samples = steering::simulate(
+                Integrator::SemiImplicitEuler,
+                Kinematics::default(),
+                1. / 60.,
+                60.,
+                |agent| steering::arrive(agent, target, 1000., max_acceleration),
+            );
+
+            assert!(samples.iter().all(|sample| sample.acceleration.length()
+                <= max_acceleration + 1e-3
+                && sample.position.y < target.y + 1.));
+            let parked = samples.last().unwrap();
+            assert!(
+                parked.position.distance(target) < 1.,
+                "parked {} away with {} of thrust",
+                parked.position.distance(target),
+                max_acceleration
+            );
+            assert!(parked.velocity.length() < 1.);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{flight, MaxAcceleration};
+use bevy::prelude::*;
+use heron::*;
+use sebaka::steering::{self, Neighbour, NeighbourGrid, SteeringBehaviour};
+
+/// Keep away from neighbours closer than `radius`, `weight` scaling the repulsion relative to the maximum acceleration
+#[derive(Component)]
+pub struct Separation {
+    pub radius: f32,
+    pub weight: f32,
+}
+
+/// Move toward the center of the neighbours within `radius`
+#[derive(Component)]
+pub struct Cohesion {
+    pub radius: f32,
+    pub weight: f32,
+}
+
+/// Match the average velocity of the neighbours within `radius`
+#[derive(Component)]
+pub struct Alignment {
+    pub radius: f32,
+    pub weight: f32,
+}
+
+/// Sum the group behaviours of each entity with its steering behaviour output, clamped to its maximum acceleration
+///
+/// Every moving entity counts as a neighbour, looked up through a grid rebuilt each frame.
+pub fn flock(
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &mut Acceleration,
+            Option<&SteeringBehaviour>,
+            Option<&Separation>,
+            Option<&Cohesion>,
+            Option<&Alignment>,
+            Option<&MaxAcceleration>,
+            Option<&flight::InertiaDampener>,
+        ),
+        (
+            Or<(With<Separation>, With<Cohesion>, With<Alignment>)>,
+            Without<flight::ManualControl>,
+        ),
+    >,
+    neighbour_query: Query<(Entity, &Transform, &Velocity)>,
+) {
+    let cell_size = query
+        .iter()
+        .flat_map(|(_, _, _, _, _, separation, cohesion, alignment, _, _)| {
+            [
+                separation.map(|s| s.radius),
+                cohesion.map(|c| c.radius),
+                alignment.map(|a| a.radius),
+            ]
+        })
+        .flatten()
+        .fold(0., f32::max);
+    if cell_size <= 0. {
+        return;
+    }
+
+    let grid = NeighbourGrid::new(
+        cell_size,
+        neighbour_query
+            .iter()
+            .map(|(entity, transform, velocity)| Neighbour {
+                entity,
+                position: transform.translation,
+                velocity: velocity.linear,
+            }),
+    );
+
+    for (
+        entity,
+        transform,
+        velocity,
+        mut acceleration,
+        behaviour,
+        separation,
+        cohesion,
+        alignment,
+        max_acceleration,
+        dampener,
+    ) in &mut query
+    {
+        let max_acceleration =
+            flight::available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+        let position = transform.translation;
+
+        let mut group = Vec3::ZERO;
+        if let Some(separation) = separation {
+            group += steering::separation(
+                entity,
+                position,
+                grid.within(position, separation.radius),
+                separation.radius,
+            ) * separation.weight;
+        }
+        if let Some(cohesion) = cohesion {
+            group += steering::cohesion(
+                entity,
+                position,
+                grid.within(position, cohesion.radius),
+                cohesion.radius,
+            ) * cohesion.weight;
+        }
+        if let Some(alignment) = alignment {
+            group += steering::alignment(
+                entity,
+                velocity.linear,
+                grid.within(position, alignment.radius),
+            ) * alignment.weight;
+        }
+
+        // Without a behaviour nothing resets the acceleration, the group behaviours are all there is
+        let base = if behaviour.is_some() {
+            acceleration.linear
+        } else {
+            Vec3::ZERO
+        };
+        acceleration.linear = (base + group * max_acceleration).clamp_length_max(max_acceleration);
+    }
+}
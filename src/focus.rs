@@ -0,0 +1,211 @@
+use crate::{localization::Localization, menu, CursorMode, OrderPreview, AMBIENT_VOLUME};
+use bevy::{
+    prelude::*,
+    window::{WindowFocused, WindowId},
+};
+use bevy_kira_audio::AudioControl;
+use heron::*;
+
+/// Time after getting the focus back during which clicks only bring the window forward, in seconds
+const CLICK_GRACE_PERIOD: f64 = 0.3;
+
+/// Ratio of the volume kept while the window is in the background
+const UNFOCUSED_VOLUME_RATIO: f64 = 0.25;
+
+/// How the game reacts to the window going to the background
+pub struct WindowFocus {
+    /// Open the pause menu when the window loses focus
+    pub auto_pause: bool,
+    regained_at: Option<f64>,
+}
+
+impl Default for WindowFocus {
+    fn default() -> Self {
+        Self {
+            auto_pause: true,
+            regained_at: None,
+        }
+    }
+}
+
+impl WindowFocus {
+    /// Whether a click happening now is the one bringing the window back, and must not reach the world
+    pub fn swallows_clicks(&self, now: f64) -> bool {
+        self.regained_at
+            .map_or(false, |regained_at| now - regained_at < CLICK_GRACE_PERIOD)
+    }
+}
+
+/// Release held keys and buttons when the window loses focus, their release would never be seen, and pause if enabled
+pub fn handle_focus_change(
+    mut commands: Commands,
+    mut focus_events: EventReader<WindowFocused>,
+    time: Res<Time>,
+    mut focus: ResMut<WindowFocus>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut buttons: ResMut<Input<MouseButton>>,
+    mut order_preview: ResMut<OrderPreview>,
+    mut cursor_mode: ResMut<CursorMode>,
+    mut physics_time: ResMut<PhysicsTime>,
+    asset_server: Res<AssetServer>,
+    localization: Res<Localization>,
+) {
+    for event in focus_events.iter() {
+        if event.id != WindowId::primary() {
+            continue;
+        }
+
+        if event.focused {
+            focus.regained_at = Some(time.seconds_since_startup());
+            continue;
+        }
+
+        // Keys still held on return must be pressed again
+        let held_keys: Vec<KeyCode> = keys.get_pressed().copied().collect();
+        for key in held_keys {
+            keys.reset(key);
+        }
+        let held_buttons: Vec<MouseButton> = buttons.get_pressed().copied().collect();
+        for button in held_buttons {
+            buttons.reset(button);
+        }
+        *order_preview = OrderPreview::Idle;
+
+        if focus.auto_pause && *cursor_mode == CursorMode::Orders {
+            menu::open_pause_menu(
+                &mut commands,
+                &asset_server,
+                &localization,
+                &mut cursor_mode,
+                &mut physics_time,
+            );
+        }
+    }
+}
+
+/// Lower the volume while the window is in the background
+pub fn duck_audio(
+    mut focus_events: EventReader<WindowFocused>,
+    audio: Res<bevy_kira_audio::Audio>,
+) {
+    for event in focus_events.iter() {
+        if event.id != WindowId::primary() {
+            continue;
+        }
+
+        audio.set_volume(if event.focused {
+            AMBIENT_VOLUME
+        } else {
+            AMBIENT_VOLUME * UNFOCUSED_VOLUME_RATIO
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bindings::{Action, KeyBindings},
+        flight::{self, ManualControl},
+    };
+
+    /// Headless app running the focus handling before manual flight, as a frame of the game would
+    fn app(auto_pause: bool) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .add_event::<WindowFocused>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<KeyBindings>()
+            .insert_resource(WindowFocus {
+                auto_pause,
+                ..default()
+            })
+            .init_resource::<OrderPreview>()
+            .init_resource::<CursorMode>()
+            .insert_resource(PhysicsTime::default())
+            .insert_resource(Localization::load("en"))
+            .add_system_to_stage(CoreStage::PreUpdate, handle_focus_change)
+            .add_system(flight::manual_flight);
+        app
+    }
+
+    fn send_focus(app: &mut App, focused: bool) {
+        app.world
+            .resource_mut::<Events<WindowFocused>>()
+            .send(WindowFocused {
+                id: WindowId::primary(),
+                focused,
+            });
+        app.update();
+    }
+
+    #[test]
+    fn losing_focus_releases_held_thrust() {
+        let mut app = app(false);
+        let ship = app
+            .world
+            .spawn()
+            .insert(ManualControl)
+            .insert(Velocity::default())
+            .insert(Acceleration::default())
+            .id();
+        let thrust = KeyBindings::default().key(Action::ThrustForward).unwrap();
+        app.world.resource_mut::<Input<KeyCode>>().press(thrust);
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .press(MouseButton::Right);
+        *app.world.resource_mut::<OrderPreview>() = OrderPreview::Holding;
+        app.update();
+        assert_ne!(
+            app.world.get::<Acceleration>(ship).unwrap().linear,
+            Vec3::ZERO
+        );
+
+        send_focus(&mut app, false);
+
+        assert!(!app.world.resource::<Input<KeyCode>>().pressed(thrust));
+        assert!(!app
+            .world
+            .resource::<Input<MouseButton>>()
+            .pressed(MouseButton::Right));
+        assert!(*app.world.resource::<OrderPreview>() == OrderPreview::Idle);
+        assert_eq!(
+            app.world.get::<Acceleration>(ship).unwrap().linear,
+            Vec3::ZERO
+        );
+
+        // Still released once the window is back, until the key is pressed again
+        send_focus(&mut app, true);
+        assert_eq!(
+            app.world.get::<Acceleration>(ship).unwrap().linear,
+            Vec3::ZERO
+        );
+        assert!(*app.world.resource::<CursorMode>() == CursorMode::Orders);
+    }
+
+    #[test]
+    fn losing_focus_pauses_when_enabled() {
+        let mut app = app(true);
+        send_focus(&mut app, false);
+
+        assert!(*app.world.resource::<CursorMode>() == CursorMode::Menu);
+    }
+
+    #[test]
+    fn clicks_are_swallowed_shortly_after_regaining_focus() {
+        let mut app = app(false);
+        let now = app.world.resource::<Time>().seconds_since_startup();
+        assert!(!app.world.resource::<WindowFocus>().swallows_clicks(now));
+
+        send_focus(&mut app, true);
+
+        let regained_at = app.world.resource::<Time>().seconds_since_startup();
+        let focus = app.world.resource::<WindowFocus>();
+        assert!(focus.swallows_clicks(regained_at));
+        assert!(focus.swallows_clicks(regained_at + CLICK_GRACE_PERIOD * 0.9));
+        assert!(!focus.swallows_clicks(regained_at + CLICK_GRACE_PERIOD * 1.1));
+        assert!(!focus.swallows_clicks(regained_at + 1.));
+    }
+}
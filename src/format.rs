@@ -0,0 +1,176 @@
+use crate::localization::Localization;
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shown in place of values that are not finite
+const INVALID: &str = "—";
+
+/// Whether a non-finite value was already reported, so that a broken value updated every frame logs only once
+static INVALID_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Distance in meters, switching to kilometers from 1 km
+pub fn format_distance(meters: f32, localization: &Localization) -> String {
+    with_unit(meters, "m", "km", localization)
+}
+
+/// Speed in meters per second, switching to kilometers per second from 1 km/s
+pub fn format_speed(meters_per_second: f32, localization: &Localization) -> String {
+    with_unit(meters_per_second, "m/s", "km/s", localization)
+}
+
+/// Duration in seconds, with minutes from one minute on
+pub fn format_duration(seconds: f32, localization: &Localization) -> String {
+    if !is_valid(seconds) {
+        return INVALID.to_string();
+    }
+
+    // Decide on the unit after rounding, so that 59.96 s shows as 1:00 rather than 60.0 s
+    let tenths = (seconds.abs() * 10.).round();
+    let sign = if seconds < 0. && tenths > 0. { "-" } else { "" };
+    if tenths < 600. {
+        format!("{}{} s", sign, decimal(tenths / 10., 1, localization))
+    } else {
+        let total = (seconds.abs()).round() as u64;
+        format!("{}{}:{:02}", sign, total / 60, total % 60)
+    }
+}
+
+/// Plain number, with one decimal below 10
+pub fn format_number(value: f32, localization: &Localization) -> String {
+    if !is_valid(value) {
+        return INVALID.to_string();
+    }
+
+    let precision = if (value.abs() * 10.).round() < 100. {
+        1
+    } else {
+        0
+    };
+    decimal(value, precision, localization)
+}
+
+/// Value with a base unit below 1000, and the unit a thousand times larger above
+fn with_unit(value: f32, unit: &str, kilo_unit: &str, localization: &Localization) -> String {
+    if !is_valid(value) {
+        return INVALID.to_string();
+    }
+
+    // Decide on the unit after rounding, so that 999.6 m shows as 1.0 km rather than 1000 m
+    let magnitude = value.abs();
+    if magnitude.round() < 1000. {
+        return format!("{} {}", decimal(value, 0, localization), unit);
+    }
+
+    let kilos = value / 1000.;
+    let precision = if (kilos.abs() * 10.).round() < 1000. {
+        1
+    } else {
+        0
+    };
+    format!("{} {}", decimal(kilos, precision, localization), kilo_unit)
+}
+
+/// Fixed precision number using the decimal separator of the current language, never showing -0
+fn decimal(value: f32, precision: usize, localization: &Localization) -> String {
+    let formatted = format!("{:.*}", precision, value);
+    let formatted =
+        if formatted.starts_with('-') && formatted[1..].chars().all(|c| c == '0' || c == '.') {
+            formatted[1..].to_string()
+        } else {
+            formatted
+        };
+
+    formatted.replace('.', localization.get("format.decimal_separator"))
+}
+
+fn is_valid(value: f32) -> bool {
+    if value.is_finite() {
+        return true;
+    }
+
+    if !INVALID_REPORTED.swap(true, Ordering::Relaxed) {
+        warn!("Non-finite value {} reached the HUD formatting", value);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_rounds_and_switches_unit_after_rounding() {
+        let localization = Localization::load("en");
+
+        assert_eq!(format_distance(0., &localization), "0 m");
+        assert_eq!(format_distance(12.4, &localization), "12 m");
+        assert_eq!(format_distance(999.4, &localization), "999 m");
+        assert_eq!(format_distance(999.6, &localization), "1.0 km");
+        assert_eq!(format_distance(1_234., &localization), "1.2 km");
+        assert_eq!(format_distance(99_960., &localization), "100 km");
+        assert_eq!(format_distance(12_345_678., &localization), "12346 km");
+    }
+
+    #[test]
+    fn negative_values_keep_their_sign_but_never_show_minus_zero() {
+        let localization = Localization::load("en");
+
+        assert_eq!(format_distance(-5.3, &localization), "-5 m");
+        assert_eq!(format_distance(-0.3, &localization), "0 m");
+        assert_eq!(format_distance(-1_500., &localization), "-1.5 km");
+        assert_eq!(format_speed(-250., &localization), "-250 m/s");
+        assert_eq!(format_duration(-3.24, &localization), "-3.2 s");
+        assert_eq!(format_duration(-0.04, &localization), "0.0 s");
+        assert_eq!(format_number(-7.26, &localization), "-7.3");
+        assert_eq!(format_number(-0.04, &localization), "0.0");
+    }
+
+    #[test]
+    fn speed_switches_to_kilometers_per_second() {
+        let localization = Localization::load("en");
+
+        assert_eq!(format_speed(250., &localization), "250 m/s");
+        assert_eq!(format_speed(1_500., &localization), "1.5 km/s");
+    }
+
+    #[test]
+    fn duration_switches_to_minutes_after_rounding() {
+        let localization = Localization::load("en");
+
+        assert_eq!(format_duration(0., &localization), "0.0 s");
+        assert_eq!(format_duration(12.34, &localization), "12.3 s");
+        assert_eq!(format_duration(59.96, &localization), "1:00");
+        assert_eq!(format_duration(125., &localization), "2:05");
+        assert_eq!(format_duration(3_600., &localization), "60:00");
+    }
+
+    #[test]
+    fn number_has_one_decimal_below_ten() {
+        let localization = Localization::load("en");
+
+        assert_eq!(format_number(4.44, &localization), "4.4");
+        assert_eq!(format_number(9.96, &localization), "10");
+        assert_eq!(format_number(42.4, &localization), "42");
+    }
+
+    #[test]
+    fn non_finite_values_show_a_dash() {
+        let localization = Localization::load("en");
+
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(format_distance(value, &localization), "—");
+            assert_eq!(format_speed(value, &localization), "—");
+            assert_eq!(format_duration(value, &localization), "—");
+            assert_eq!(format_number(value, &localization), "—");
+        }
+    }
+
+    #[test]
+    fn decimal_separator_follows_the_language() {
+        let localization = Localization::load("fr");
+
+        assert_eq!(format_distance(1_234., &localization), "1,2 km");
+        assert_eq!(format_duration(12.34, &localization), "12,3 s");
+        assert_eq!(format_number(4.44, &localization), "4,4");
+    }
+}
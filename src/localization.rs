@@ -0,0 +1,77 @@
+use crate::bindings::{Action, KeyBindings};
+use bevy::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::Mutex,
+};
+
+/// Languages shipping a table in assets/lang, cycled with F9
+pub const LANGUAGES: [&str; 2] = ["en", "fr"];
+
+/// Table of UI strings for the current language
+pub struct Localization {
+    language: String,
+    strings: HashMap<String, String>,
+    /// Keys already reported as missing, so that each one is only logged once
+    missing: Mutex<HashSet<String>>,
+}
+
+impl Localization {
+    /// Load assets/lang/<language>.ron, falling back to an empty table (every key shows as is) on error
+    pub fn load(language: &str) -> Self {
+        let path = format!("assets/lang/{}.ron", language);
+        let strings = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|content| ron::from_str(&content).map_err(|error| error.to_string()))
+            .unwrap_or_else(|error| {
+                warn!("Could not load language table {}: {}", path, error);
+                HashMap::new()
+            });
+
+        Self {
+            language: language.to_string(),
+            strings,
+            missing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Translated string for the key, or the key itself if the current language lacks it
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.strings.get(key) {
+            Some(string) => string,
+            None => {
+                if self.missing.lock().unwrap().insert(key.to_string()) {
+                    warn!("Missing '{}' string for language {}", key, self.language);
+                }
+                key
+            }
+        }
+    }
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self::load(LANGUAGES[0])
+    }
+}
+
+/// Switch to the next language with F9, UI systems pick up the new strings on their next update
+pub fn cycle_language(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut localization: ResMut<Localization>,
+) {
+    if bindings.just_pressed(&keys, Action::Language) {
+        let index = LANGUAGES
+            .iter()
+            .position(|&language| language == localization.language())
+            .unwrap_or(0);
+        *localization = Localization::load(LANGUAGES[(index + 1) % LANGUAGES.len()]);
+        info!("Language: {}", localization.language());
+    }
+}
@@ -1,20 +1,41 @@
 use bevy::{
+    input::mouse::MouseWheel,
     prelude::*,
     render::{
         camera::RenderTarget, render_resource::WgpuFeatures, settings::WgpuSettings,
         texture::ImageSettings,
     },
-    window::WindowMode,
+    window::{WindowMode, WindowSettings},
 };
 use bevy_hanabi::*;
 use bevy_kira_audio::prelude::*;
 use bevy_pancam::{PanCam, PanCamPlugin};
 use bevy_prototype_debug_lines::*;
 use heron::*;
+use sebaka::steering::{self, Kinematics, SteeringBehaviour};
 use std::f32::consts::PI;
-use steering::SteeringBehaviour;
 
-mod steering;
+mod avoidance;
+mod bindings;
+mod build;
+mod chart;
+mod cleanup;
+mod debug;
+mod demo;
+mod exhaust;
+mod flight;
+mod flocking;
+mod focus;
+mod format;
+mod localization;
+mod menu;
+mod sanity;
+mod session;
+mod stance;
+mod telemetry;
+mod visuals;
+mod wind;
+mod zones;
 
 fn main() {
     let window = WindowDescriptor {
@@ -28,46 +49,223 @@ fn main() {
         .features
         .set(WgpuFeatures::VERTEX_WRITABLE_STORAGE, true);
 
-    App::new()
-        .insert_resource(window)
+    let launch_options = session::LaunchOptions::from_args();
+    let fast_start = launch_options.fast_start;
+
+    let mut app = App::new();
+    app.insert_resource(window)
         .insert_resource(options)
         .insert_resource(ImageSettings::default_nearest())
         .insert_resource(Gravity::from(Vec3::new(0., 0., 0.)))
         .insert_resource(ClearColor(Color::rgb(0.0196, 0.0235, 0.0235)))
         .insert_resource(MouseScreenPosition(None))
         .insert_resource(MouseWorldPosition(None))
-        .add_plugins(DefaultPlugins)
-        .add_plugin(AudioPlugin)
-        .add_plugin(PanCamPlugin::default())
+        .insert_resource(CommandedSpeed::default())
+        .insert_resource(CursorMode::default())
+        .insert_resource(bindings::KeyBindings::default())
+        .insert_resource(bindings::KeyReference::default())
+        .insert_resource(debug::DebugFrame::default())
+        .insert_resource(debug::DebugDrawConfig::default())
+        .insert_resource(localization::Localization::default())
+        .insert_resource(visuals::MotionStretch::default())
+        .insert_resource(wind::SolarWind::default())
+        .insert_resource(chart::StripChart::default())
+        .insert_resource(telemetry::TelemetryRecorder::default())
+        .insert_resource(OrderPreview::default())
+        .insert_resource(focus::WindowFocus::default())
+        .insert_resource(launch_options)
+        .insert_resource(session::Shutdown::default())
+        // Closing the window goes through the shutdown sequence instead, which exits once finalized
+        .insert_resource(WindowSettings {
+            close_when_requested: false,
+            ..default()
+        })
+        .add_plugins(DefaultPlugins);
+
+    // Audio initialization is what makes startup slow, fast start skips it altogether
+    if !fast_start {
+        app.add_plugin(AudioPlugin);
+    }
+
+    app.add_plugin(PanCamPlugin::default())
         .add_plugin(DebugLinesPlugin::default())
         .add_plugin(PhysicsPlugin::default())
         .add_plugin(HanabiPlugin)
         .add_startup_system(setup)
-        .add_startup_system(start_ambient_music)
+        .add_startup_system(start_ambient_music.with_run_criteria(session::audio_enabled))
+        .add_startup_system_to_stage(StartupStage::PostStartup, session::restore_session)
+        .add_startup_system_to_stage(
+            StartupStage::PostStartup,
+            demo::spawn_demo.with_run_criteria(session::demo_enabled),
+        )
+        .add_startup_system(demo::spawn_boids.with_run_criteria(session::demo_enabled))
+        .add_startup_system(flight::spawn_flight_mode_label)
+        .add_startup_system(debug::spawn_slip_angle_label)
+        .add_startup_system(chart::spawn_strip_chart_label)
         .add_system(orientation)
+        .add_system(demo::orbit)
+        .add_system(stance::hold_strafe_heading)
         .add_system(thruster_power)
-        .add_system(steering_behaviour)
-        // .add_system(arrive_to_movement_marker)
+        .add_system(exhaust::cycle_exhaust_profile.with_run_criteria(menu::not_in_menu))
+        .add_system(exhaust::apply_exhaust_profile)
+        .add_system(visuals::toggle_motion_stretch.with_run_criteria(menu::not_in_menu))
+        .add_system(visuals::motion_stretch)
+        .add_system(visuals::animate_pings)
+        .add_system(visuals::order_flash)
+        .add_system(zones::update_speed_caps.before(SteeringSystem::Behaviour))
+        .add_system(steering_behaviour.label(SteeringSystem::Behaviour))
+        .add_system(zones::clamp_manual_speed)
+        .add_system(zones::debug_speed_zones)
+        .add_system(flight::toggle_flight_modes.with_run_criteria(menu::not_in_menu))
+        .add_system(flight::toggle_inertia_dampener.with_run_criteria(menu::not_in_menu))
+        .add_system(flight::apply_inertia_dampener)
+        .add_system(
+            flight::manual_flight
+                .with_run_criteria(menu::not_in_menu)
+                .label(SteeringSystem::Behaviour),
+        )
+        .add_system(
+            flocking::flock
+                .label(SteeringSystem::Flocking)
+                .after(SteeringSystem::Behaviour),
+        )
+        .add_system(
+            smooth_steering_transitions
+                .label(SteeringSystem::Transition)
+                .after(SteeringSystem::Flocking),
+        )
+        .add_system(
+            limit_jerk
+                .label(SteeringSystem::Limit)
+                .after(SteeringSystem::Transition),
+        )
+        .add_system(
+            avoidance::avoid_obstacles
+                .label(SteeringSystem::Avoidance)
+                .after(SteeringSystem::Limit),
+        )
+        .add_system(sanity::guard_non_finite.after(SteeringSystem::Avoidance))
+        .add_system(telemetry::toggle_telemetry.with_run_criteria(menu::not_in_menu))
+        .add_system(telemetry::record_telemetry.after(SteeringSystem::Avoidance))
+        .add_system(chart::toggle_strip_chart.with_run_criteria(menu::not_in_menu))
+        .add_system(chart::record_strip_chart.after(SteeringSystem::Avoidance))
+        .add_system(chart::draw_strip_chart)
+        .add_system_to_stage(CoreStage::PostUpdate, telemetry::flush_telemetry)
+        .add_system_to_stage(CoreStage::PostUpdate, cleanup::despawn_orphans)
+        .add_system(session::request_shutdown_on_close)
+        .add_system(session::fade_out_audio_on_shutdown.with_run_criteria(session::audio_enabled))
+        .add_system(session::exit_after_shutdown)
+        .add_system_to_stage(CoreStage::Last, session::save_session)
+        .add_system_to_stage(CoreStage::Last, telemetry::flush_telemetry_on_exit)
+        // In PreUpdate, so that targets spawned by commands during the previous frame already exist
+        .add_system_to_stage(CoreStage::PreUpdate, cleanup::clear_stale_steering_targets)
+        // In PreUpdate too, so that ships spawned during the previous frame are capped before steering
+        .add_system_to_stage(CoreStage::PreUpdate, zones::insert_speed_caps)
+        .add_system(flight::flight_mode_label)
+        .add_system(wind::toggle_solar_wind.with_run_criteria(menu::not_in_menu))
+        .add_system(wind::apply_solar_wind)
         .add_system(track_mouse)
+        .add_system(adjust_commanded_speed)
+        .add_system(commanded_speed_label)
         .add_system(move_movement_marker_on_click)
-        .add_system(debug_velocity.chain(debug_acceleration))
-        .add_system(debug_movement_marker)
-        .add_system(bevy::window::close_on_esc)
+        .add_system(order_preview)
+        .add_system(complete_orders)
+        .add_system(stance::toggle_hold_position.before(SteeringSystem::Behaviour))
+        .add_system(stance::release_hold_on_order.before(SteeringSystem::Behaviour))
+        .add_system(stance::debug_hold_anchors)
+        .add_system(build::toggle_build_mode)
+        .add_system(build::update_station_ghost)
+        .add_system(build::place_station)
+        .add_system(localization::cycle_language)
+        .add_system(debug::toggle_debug_frame.with_run_criteria(menu::not_in_menu))
+        .add_system(debug::cycle_debug_draw_preset.with_run_criteria(menu::not_in_menu))
+        .add_system(debug::cycle_debug_color_mode.with_run_criteria(menu::not_in_menu))
+        .add_system(debug::debug_velocity.chain(debug::debug_acceleration))
+        .add_system(debug::debug_slip_angle)
+        .add_system(debug::debug_movement_marker)
+        .add_system(debug::debug_steering_target)
+        .add_system(debug::debug_path)
+        .add_system(debug::debug_hiding_spots)
+        .add_system(menu::toggle_pause_menu)
+        .add_system(focus::handle_focus_change)
+        .add_system(focus::duck_audio.with_run_criteria(session::audio_enabled))
+        .add_system(menu::pause_menu_buttons)
+        .add_system(menu::pause_menu_setting_labels)
+        .add_system(bindings::toggle_key_reference)
         .run();
 }
 
+/// Systems writing Acceleration, post-processing systems run after them
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+enum SteeringSystem {
+    Behaviour,
+    /// Group behaviours summed with the behaviour output
+    Flocking,
+    /// Blending across behaviour changes
+    Transition,
+    /// Limits applied on top of the behaviours output
+    Limit,
+    /// Hard overrides such as obstacle avoidance, never blended nor limited, Acceleration is final afterwards
+    Avoidance,
+}
+
 #[derive(Default)]
 struct MouseScreenPosition(Option<Vec2>);
 
 #[derive(Default)]
 struct MouseWorldPosition(Option<Vec3>);
 
+/// Ratio of MaxVelocity applied to the next move order, adjusted with Ctrl + mouse wheel
+struct CommandedSpeed(f32);
+
+impl Default for CommandedSpeed {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// What clicks in the world currently mean, so that modal tools never issue move orders
+#[derive(Default, PartialEq, Eq)]
+enum CursorMode {
+    #[default]
+    Orders,
+    Build,
+    /// A menu is open, the world does not receive clicks
+    Menu,
+}
+
+/// State of the order being prepared while the order button is held
+#[derive(Default, PartialEq, Eq)]
+enum OrderPreview {
+    #[default]
+    Idle,
+    Holding,
+    /// Cancelled with Esc, releasing the button will not issue the order
+    Cancelled,
+}
+
+/// Distance to the movement marker under which an order is considered completed
+const ORDER_COMPLETION_DISTANCE: f32 = 30.;
+
+/// Gap left between the hulls when an order targets a large object
+const APPROACH_MARGIN: f32 = 50.;
+
 #[derive(Component)]
 struct MainCamera;
 
 #[derive(Component)]
 struct MovementMarker;
 
+/// Per-order speed cap (ratio of MaxVelocity) carried by the movement marker, cleared once the order is completed
+#[derive(Component)]
+struct OrderSpeed(f32);
+
+#[derive(Component)]
+struct CommandedSpeedLabel;
+
+#[derive(Component)]
+struct OrderPreviewLabel;
+
 #[derive(Component)]
 struct Spaceship;
 
@@ -75,6 +273,7 @@ struct Spaceship;
 struct ThrusterEffect {
     size: f32,
     angle: f32,
+    nozzle: exhaust::Nozzle,
 }
 
 #[derive(Component)]
@@ -83,24 +282,96 @@ struct MaxVelocity(f32);
 #[derive(Component)]
 struct MaxAcceleration(f32);
 
+/// How fast acceleration may change, per second
+#[derive(Component)]
+struct MaxJerk(f32);
+
+/// Acceleration applied on the previous frame, used for jerk limiting
+#[derive(Component)]
+struct PreviousAcceleration(Vec3);
+
+/// Blend of the steering acceleration when the SteeringBehaviour changes, so that the thrust does not flip at once
+#[derive(Component)]
+struct SteeringTransition {
+    duration: f32,
+    /// Steering acceleration of the last frame
+    last: Vec3,
+    /// Steering acceleration when the behaviour changed
+    from: Vec3,
+    elapsed: f32,
+}
+
+impl Default for SteeringTransition {
+    fn default() -> Self {
+        Self {
+            duration: 0.3,
+            last: Vec3::ZERO,
+            from: Vec3::ZERO,
+            elapsed: f32::INFINITY,
+        }
+    }
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    key_bindings: Res<bindings::KeyBindings>,
     mut effects: ResMut<Assets<EffectAsset>>,
 ) {
+    let exhaust_effects = exhaust::ExhaustEffects::new(&mut effects);
+
     // Spawn the main camera
     commands
         .spawn()
         .insert_bundle(Camera2dBundle::default())
         .insert(MainCamera)
         .insert(PanCam {
-            grab_buttons: vec![MouseButton::Left],
+            grab_buttons: key_bindings
+                .button(bindings::Action::Pan)
+                .into_iter()
+                .collect(),
             enabled: true,
             zoom_to_cursor: true,
             min_scale: 0.01,
             max_scale: Some(40.),
         });
 
+    // Spawn the commanded speed label, following the cursor while Ctrl is held
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 14.,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            }),
+        )
+        .insert(CommandedSpeedLabel);
+
+    // Spawn the order preview label, following the cursor while the order button is held
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 14.,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            }),
+        )
+        .insert(OrderPreviewLabel);
+
     // Spawn the movement marker, one and only one !
     let movement_marker = commands
         .spawn()
@@ -116,6 +387,11 @@ fn setup(
         .insert(RigidBody::Dynamic)
         .insert(Velocity::from_linear(Vec3::ZERO))
         .insert(Acceleration::from_linear(Vec3::ZERO))
+        .insert(SteeringTransition::default())
+        .insert(zones::SpeedCap::default())
+        .insert(exhaust::ExhaustProfile::default())
+        .insert(flight::InertiaDampener::default())
+        .insert(avoidance::ObstacleAvoidance::default())
         .insert(SteeringBehaviour::Seek {
             target: movement_marker,
         })
@@ -123,51 +399,17 @@ fn setup(
             radius: 100.0,
             half_segment: 25.0,
         })
-        .insert_bundle(SpriteBundle {
-            texture: asset_server.load("ship666.png"),
-            ..default()
-        })
+        .insert_bundle(VisibilityBundle::default())
         .with_children(|builder| {
-            let main_effect = effects.add(
-                EffectAsset {
-                    name: "thruster".into(),
-                    capacity: 32768,
-                    spawner: Spawner::rate(1000.0.into()),
-                    ..Default::default()
-                }
-                .init(PositionCone3dModifier {
-                    speed: 250.0.into(),
-                    dimension: ShapeDimension::Volume,
-                    base_radius: 25.,
-                    top_radius: 1.,
-                    height: 50.,
-                })
-                .init(ParticleLifetimeModifier { lifetime: 1.5 })
-                .render(SizeOverLifetimeModifier {
-                    gradient: {
-                        let mut gradient = Gradient::new();
-                        gradient.add_key(0.00, Vec2::splat(6.8));
-                        gradient.add_key(0.05, Vec2::splat(4.5));
-                        gradient.add_key(0.10, Vec2::splat(1.2));
-                        gradient.add_key(0.15, Vec2::splat(0.2));
-                        gradient.add_key(0.25, Vec2::splat(8.5));
-                        gradient.add_key(1.00, Vec2::splat(0.5));
-                        gradient
-                    },
+            // The hull sprite lives on its own child, so that visual effects never scale the collider
+            builder
+                .spawn_bundle(SpriteBundle {
+                    texture: asset_server.load("ship666.png"),
+                    ..default()
                 })
-                .render(ColorOverLifetimeModifier {
-                    gradient: {
-                        let mut gradient = Gradient::new();
-                        gradient.add_key(0.00, Vec4::new(1.0, 0.8, 0.3, 1.0));
-                        gradient.add_key(0.03, Vec4::new(1.0, 0.66, 0.0, 1.0));
-                        gradient.add_key(0.10, Vec4::new(1.0, 0.55, 0.0, 0.8));
-                        gradient.add_key(0.15, Vec4::new(0.0, 0.0, 0.0, 0.0));
-                        gradient.add_key(0.25, Vec4::new(0.56, 0.52, 0.51, 0.8));
-                        gradient.add_key(1.00, Vec4::new(0.56, 0.52, 0.51, 0.0));
-                        gradient
-                    },
-                }),
-            );
+                .insert(visuals::HullSprite);
+
+            let profile = exhaust::ExhaustProfile::default();
 
             let mut transform = Transform::default();
             transform.rotation = Quat::from_axis_angle(Vec3::Z, PI);
@@ -176,129 +418,62 @@ fn setup(
             builder
                 .spawn_bundle(ParticleEffectBundle {
                     // Assign the Z layer so it appears in the egui inspector and can be modified at runtime
-                    effect: ParticleEffect::new(main_effect).with_z_layer_2d(Some(0.1)),
+                    effect: ParticleEffect::new(
+                        exhaust_effects.get(profile, exhaust::Nozzle::Main),
+                    )
+                    .with_z_layer_2d(Some(0.1)),
                     transform,
                     ..default()
                 })
                 .insert(ThrusterEffect {
                     size: 1.0,
                     angle: PI,
+                    nozzle: exhaust::Nozzle::Main,
                 });
 
-            let secondary_effect = effects.add(
-                EffectAsset {
-                    name: "thruster".into(),
-                    capacity: 32768,
-                    spawner: Spawner::rate(1000.0.into()),
-                    ..Default::default()
-                }
-                .init(PositionCone3dModifier {
-                    speed: 250.0.into(),
-                    dimension: ShapeDimension::Volume,
-                    base_radius: 5.,
-                    top_radius: 1.,
-                    height: 50.,
-                })
-                .init(ParticleLifetimeModifier { lifetime: 1.5 })
-                .render(SizeOverLifetimeModifier {
-                    gradient: {
-                        let mut gradient = Gradient::new();
-                        gradient.add_key(0.00, Vec2::splat(6.8));
-                        gradient.add_key(0.05, Vec2::splat(4.5));
-                        gradient.add_key(0.10, Vec2::splat(1.2));
-                        gradient.add_key(0.15, Vec2::splat(0.2));
-                        gradient.add_key(0.25, Vec2::splat(8.5));
-                        gradient.add_key(1.00, Vec2::splat(0.5));
-                        gradient
-                    },
-                })
-                .render(ColorOverLifetimeModifier {
-                    gradient: {
-                        let mut gradient = Gradient::new();
-                        gradient.add_key(0.00, Vec4::new(1.0, 0.8, 0.3, 1.0));
-                        gradient.add_key(0.03, Vec4::new(1.0, 0.66, 0.0, 1.0));
-                        gradient.add_key(0.10, Vec4::new(1.0, 0.55, 0.0, 0.8));
-                        gradient.add_key(0.15, Vec4::new(0.0, 0.0, 0.0, 0.0));
-                        gradient.add_key(0.25, Vec4::new(0.56, 0.52, 0.51, 0.8));
-                        gradient.add_key(1.00, Vec4::new(0.56, 0.52, 0.51, 0.0));
-                        gradient
-                    },
-                }),
-            );
-
-            let mut transform = Transform::default();
-            transform.rotation = Quat::from_axis_angle(Vec3::Z, 0.);
-            transform.translation = Vec3::new(-50., 205., 0.);
-
-            builder
-                .spawn_bundle(ParticleEffectBundle {
-                    // Assign the Z layer so it appears in the egui inspector and can be modified at runtime
-                    effect: ParticleEffect::new(secondary_effect).with_z_layer_2d(Some(0.1)),
-                    transform,
-                    ..default()
-                })
-                .insert(ThrusterEffect {
-                    size: 0.4,
-                    angle: 0.,
-                });
+            for x in [-50., 50.] {
+                let mut transform = Transform::default();
+                transform.rotation = Quat::from_axis_angle(Vec3::Z, 0.);
+                transform.translation = Vec3::new(x, 205., 0.);
 
-            let secondary_effect = effects.add(
-                EffectAsset {
-                    name: "thruster".into(),
-                    capacity: 32768,
-                    spawner: Spawner::rate(1000.0.into()),
-                    ..Default::default()
-                }
-                .init(PositionCone3dModifier {
-                    speed: 250.0.into(),
-                    dimension: ShapeDimension::Volume,
-                    base_radius: 5.,
-                    top_radius: 1.,
-                    height: 50.,
-                })
-                .init(ParticleLifetimeModifier { lifetime: 1.5 })
-                .render(SizeOverLifetimeModifier {
-                    gradient: {
-                        let mut gradient = Gradient::new();
-                        gradient.add_key(0.00, Vec2::splat(6.8));
-                        gradient.add_key(0.05, Vec2::splat(4.5));
-                        gradient.add_key(0.10, Vec2::splat(1.2));
-                        gradient.add_key(0.15, Vec2::splat(0.2));
-                        gradient.add_key(0.25, Vec2::splat(8.5));
-                        gradient.add_key(1.00, Vec2::splat(0.5));
-                        gradient
-                    },
-                })
-                .render(ColorOverLifetimeModifier {
-                    gradient: {
-                        let mut gradient = Gradient::new();
-                        gradient.add_key(0.00, Vec4::new(1.0, 0.8, 0.3, 1.0));
-                        gradient.add_key(0.03, Vec4::new(1.0, 0.66, 0.0, 1.0));
-                        gradient.add_key(0.10, Vec4::new(1.0, 0.55, 0.0, 0.8));
-                        gradient.add_key(0.15, Vec4::new(0.0, 0.0, 0.0, 0.0));
-                        gradient.add_key(0.25, Vec4::new(0.56, 0.52, 0.51, 0.8));
-                        gradient.add_key(1.00, Vec4::new(0.56, 0.52, 0.51, 0.0));
-                        gradient
-                    },
-                }),
-            );
+                builder
+                    .spawn_bundle(ParticleEffectBundle {
+                        // Assign the Z layer so it appears in the egui inspector and can be modified at runtime
+                        effect: ParticleEffect::new(
+                            exhaust_effects.get(profile, exhaust::Nozzle::Maneuvering),
+                        )
+                        .with_z_layer_2d(Some(0.1)),
+                        transform,
+                        ..default()
+                    })
+                    .insert(ThrusterEffect {
+                        size: 0.4,
+                        angle: 0.,
+                        nozzle: exhaust::Nozzle::Maneuvering,
+                    });
+            }
 
-            let mut transform = Transform::default();
-            transform.rotation = Quat::from_axis_angle(Vec3::Z, 0.);
-            transform.translation = Vec3::new(50., 205., 0.);
+            // Lateral RCS, doing the work while strafing
+            for (x, angle) in [(-60., PI / 2.), (60., 3. * PI / 2.)] {
+                let mut transform = Transform::default();
+                transform.rotation = Quat::from_axis_angle(Vec3::Z, angle);
+                transform.translation = Vec3::new(x, 0., 0.);
 
-            builder
-                .spawn_bundle(ParticleEffectBundle {
-                    // Assign the Z layer so it appears in the egui inspector and can be modified at runtime
-                    effect: ParticleEffect::new(secondary_effect.clone())
+                builder
+                    .spawn_bundle(ParticleEffectBundle {
+                        effect: ParticleEffect::new(
+                            exhaust_effects.get(profile, exhaust::Nozzle::Maneuvering),
+                        )
                         .with_z_layer_2d(Some(0.1)),
-                    transform,
-                    ..default()
-                })
-                .insert(ThrusterEffect {
-                    size: 0.4,
-                    angle: 0.,
-                });
+                        transform,
+                        ..default()
+                    })
+                    .insert(ThrusterEffect {
+                        size: 0.4,
+                        angle,
+                        nozzle: exhaust::Nozzle::Maneuvering,
+                    });
+            }
         });
 
     // Spawn some asteroids
@@ -311,17 +486,26 @@ fn setup(
     //         transform: Transform::from_translation(Vec3::new(0., 2000., 0.)),
     //         ..default()
     //     });
+
+    commands.insert_resource(exhaust_effects);
 }
 
+/// Volume of the ambient music
+const AMBIENT_VOLUME: f64 = 0.3;
+
 fn start_ambient_music(asset_server: Res<AssetServer>, audio: Res<bevy_kira_audio::Audio>) {
     audio
         .play(asset_server.load("ambient.ogg"))
         .looped()
-        .with_volume(0.3);
+        .with_volume(AMBIENT_VOLUME);
 }
 
+/// Volume of the blip confirming an order
+const ORDER_SOUND_VOLUME: f64 = 0.5;
+
 /// Update orientation according to velocity vector (not really the desired behaviour, but it will do for now)
-fn orientation(mut query: Query<(&mut Transform, &Velocity)>) {
+/// Strafing ships are left out, they keep their heading
+fn orientation(mut query: Query<(&mut Transform, &Velocity), Without<stance::Strafe>>) {
     for (mut transform, velocity) in &mut query {
         if velocity.linear.length_squared() > f32::EPSILON {
             let angle = {
@@ -359,15 +543,22 @@ fn thruster_power(
                 let max_acceleration = max_acceleration
                     .map(|m| m.0)
                     .unwrap_or(current_acceleration);
-                let current_power = current_acceleration / max_acceleration;
+                // Without a MaxAcceleration the current one is the maximum, which is zero at rest
+                let current_power = if max_acceleration > f32::EPSILON {
+                    current_acceleration / max_acceleration
+                } else {
+                    0.
+                };
 
+                // Exhaust has to point against the acceleration, both compared in the hull frame so that strafing lights up the side thrusters
                 // UP is 0, LEFT is PI/2, DOWN is PI, RIGHT is 3/2PI, UP is 2PI
-                let ship_angle = transform.rotation.to_axis_angle().1;
-                let accel_angle = Vec2::Y.angle_between(acceleration.linear.truncate()) + PI;
-                let accel_angle = (2. * PI - (ship_angle - accel_angle).abs())
-                    .min((ship_angle - accel_angle).abs());
-                let alignement =
-                    (1. / (accel_angle / PI - thruster.angle / PI).abs() - 1.5).clamp(0., 5.);
+                let exhaust = -steering::to_hull_frame(transform.rotation, acceleration.linear);
+                let nozzle = (Quat::from_rotation_z(thruster.angle) * Vec3::Y).truncate();
+                let alignement = if exhaust.length_squared() > f32::EPSILON {
+                    (1. / (nozzle.angle_between(exhaust).abs() / PI) - 1.5).clamp(0., 5.)
+                } else {
+                    0.
+                };
 
                 effect.set_spawner(Spawner::rate(
                     (current_power * alignement * thruster.size * 200.).into(),
@@ -379,164 +570,616 @@ fn thruster_power(
 
 /// Update acceleration according to movement marker position
 fn steering_behaviour(
-    mut query: Query<(
-        &SteeringBehaviour,
-        &Transform,
-        &Velocity,
-        Option<&MaxVelocity>,
-        &mut Acceleration,
-        Option<&MaxAcceleration>,
-    )>,
-    target_query: Query<&Transform, With<MovementMarker>>,
+    mut query: Query<
+        (
+            &mut SteeringBehaviour,
+            &Transform,
+            &Velocity,
+            Option<&MaxVelocity>,
+            &mut Acceleration,
+            Option<&MaxAcceleration>,
+            Option<&zones::SpeedCap>,
+            Option<&flight::InertiaDampener>,
+            Option<&stance::Strafe>,
+        ),
+        Without<flight::ManualControl>,
+    >,
+    target_query: Query<(&Transform, Option<&OrderSpeed>, Option<&Velocity>)>,
+    obstacle_query: Query<(&Transform, &CollisionShape), With<steering::Obstacle>>,
 ) {
-    for (behaviour, transform, velocity, max_velocity, mut acceleration, max_acceleration) in
-        &mut query
+    for (
+        mut behaviour,
+        transform,
+        velocity,
+        max_velocity,
+        mut acceleration,
+        max_acceleration,
+        speed_cap,
+        dampener,
+        strafe,
+    ) in &mut query
     {
-        match behaviour {
-            SteeringBehaviour::Seek { target } => {
-                let target = target_query.get(*target).unwrap();
-                acceleration.linear = {
-                    let max_velocity = max_velocity.map(|m| m.0).unwrap_or(1000.);
-                    let max_acceleration = max_acceleration.map(|m| m.0).unwrap_or(100.);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: velocity.linear,
+        };
+        let max_acceleration =
+            flight::available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+        // Speed zones cap the velocity early enough for the ship to brake before entering them
+        let max_velocity = max_velocity
+            .map(|m| m.0)
+            .unwrap_or(1000.)
+            .min(speed_cap.map(|c| c.speed).unwrap_or(f32::INFINITY));
 
-                    let difference = target.translation - transform.translation;
-                    let desired_velocity = difference.normalize_or_zero() * max_velocity;
-                    let steering =
-                        (desired_velocity - velocity.linear).clamp_length_max(max_acceleration);
+        // Only written when a path advances, so that change detection keeps meaning a new behaviour or waypoint
+        let mut next_index = None;
 
-                    steering
+        match &*behaviour {
+            SteeringBehaviour::Seek { target } => {
+                // Targets spawned this frame are not queryable yet
+                let (target, order_speed, _) = match target_query.get(*target) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                let max_velocity = max_velocity * order_speed.map(|s| s.0).unwrap_or(1.);
+                // A strafe ends stopped on the point, it would otherwise orbit it sideways
+                acceleration.linear = if strafe.is_some() {
+                    steering::arrive(agent, target.translation, max_velocity, max_acceleration)
+                } else {
+                    steering::seek(agent, target.translation, max_velocity, max_acceleration)
                 };
             }
-            SteeringBehaviour::Arrive {
-                target,
-                final_angle,
-            } => {
-                let target = target_query.get(*target).unwrap();
-                acceleration.linear = {
-                    let max_velocity = max_velocity.map(|m| m.0).unwrap_or(1000.);
-                    let max_acceleration = max_acceleration.map(|m| m.0).unwrap_or(100.);
-
-                    let difference = target.translation - transform.translation;
-                    let desired_velocity = difference.normalize_or_zero() * max_velocity;
-                    let steering = (desired_velocity
-                        - velocity.linear
-                            * (1. + velocity.linear.length() * 10. / difference.length().max(1.)))
-                    .clamp_length_max(max_acceleration);
-
-                    steering
+            SteeringBehaviour::Arrive { target, .. } => {
+                let (target, order_speed, _) = match target_query.get(*target) {
+                    Ok(target) => target,
+                    Err(_) => continue,
                 };
+                let max_velocity = max_velocity * order_speed.map(|s| s.0).unwrap_or(1.);
+                acceleration.linear =
+                    steering::arrive(agent, target.translation, max_velocity, max_acceleration);
             }
             SteeringBehaviour::Persue {
                 target,
                 min_distance,
-            } => todo!(),
-            SteeringBehaviour::Flee { target } => todo!(),
+            } => {
+                let (target, _, target_velocity) = match target_query.get(*target) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                let target = Kinematics {
+                    position: target.translation,
+                    velocity: target_velocity.map(|v| v.linear).unwrap_or(Vec3::ZERO),
+                };
+                acceleration.linear =
+                    steering::persue(agent, target, *min_distance, max_velocity, max_acceleration);
+            }
+            SteeringBehaviour::Flee { target } => {
+                let (target, _, _) = match target_query.get(*target) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                acceleration.linear =
+                    steering::flee(agent, target.translation, max_velocity, max_acceleration);
+            }
             SteeringBehaviour::Evade {
                 target,
                 min_distance,
-            } => todo!(),
+            } => {
+                let (target, _, target_velocity) = match target_query.get(*target) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                let threat = Kinematics {
+                    position: target.translation,
+                    velocity: target_velocity.map(|v| v.linear).unwrap_or(Vec3::ZERO),
+                };
+                acceleration.linear = steering::evade(
+                    agent,
+                    threat,
+                    min_distance.unwrap_or(steering::DEFAULT_EVADE_DISTANCE),
+                    max_velocity,
+                    max_acceleration,
+                );
+            }
             SteeringBehaviour::FollowPath {
                 path,
                 current_index,
-            } => todo!(),
+            } => {
+                let (path_acceleration, index) = steering::follow_path(
+                    agent,
+                    path,
+                    *current_index,
+                    steering::PATH_ARRIVAL_RADIUS,
+                    max_velocity,
+                    max_acceleration,
+                );
+                acceleration.linear = path_acceleration;
+                if index != *current_index {
+                    next_index = Some(index);
+                }
+            }
             SteeringBehaviour::Interpose {
                 from_target,
                 to_target,
-            } => todo!(),
-            SteeringBehaviour::Hide { target } => todo!(),
+            } => {
+                let kinematics = |target: Entity| {
+                    let (transform, _, velocity) = target_query.get(target).ok()?;
+                    Some(Kinematics {
+                        position: transform.translation,
+                        velocity: velocity.map(|v| v.linear).unwrap_or(Vec3::ZERO),
+                    })
+                };
+                // A target despawned this frame is dealt with by the cleanup, turning Interpose into Arrive at the survivor
+                acceleration.linear = match (kinematics(*from_target), kinematics(*to_target)) {
+                    (Some(from), Some(to)) => {
+                        steering::interpose(agent, from, to, max_velocity, max_acceleration)
+                    }
+                    _ => continue,
+                };
+            }
+            SteeringBehaviour::Hide { target } => {
+                let (target, _, target_velocity) = match target_query.get(*target) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                let threat = Kinematics {
+                    position: target.translation,
+                    velocity: target_velocity.map(|v| v.linear).unwrap_or(Vec3::ZERO),
+                };
+                let obstacles = obstacle_query.iter().map(|(transform, shape)| {
+                    (transform.translation, build::bounding_radius(shape))
+                });
+                // Nothing to hide behind, just keep away
+                acceleration.linear =
+                    match steering::hiding_spot(agent.position, threat.position, obstacles) {
+                        Some(spot) => steering::arrive(agent, spot, max_velocity, max_acceleration),
+                        None => steering::evade(
+                            agent,
+                            threat,
+                            steering::DEFAULT_EVADE_DISTANCE,
+                            max_velocity,
+                            max_acceleration,
+                        ),
+                    };
+            }
+            SteeringBehaviour::OffsetPursuit { leader, offset } => {
+                let (leader_transform, _, leader_velocity) = match target_query.get(*leader) {
+                    Ok(leader) => leader,
+                    Err(_) => continue,
+                };
+                let leader = Kinematics {
+                    position: leader_transform.translation,
+                    velocity: leader_velocity.map(|v| v.linear).unwrap_or(Vec3::ZERO),
+                };
+                acceleration.linear = steering::offset_pursuit(
+                    agent,
+                    leader,
+                    leader_transform.rotation,
+                    *offset,
+                    max_velocity,
+                    max_acceleration,
+                );
+            }
+        }
+
+        // Borrowing mutably marks the component changed, so only do it when there is something to write
+        if let Some(index) = next_index {
+            if let SteeringBehaviour::FollowPath { current_index, .. } = &mut *behaviour {
+                *current_index = index;
+            }
         }
     }
 }
 
-/// Update acceleration according to movement marker position
-fn arrive_to_movement_marker(
+/// Blend steering output from the previous behaviour to the new one after a SteeringBehaviour change.
+/// Manual control overrides steering entirely and obstacle avoidance comes afterwards, neither is blended.
+fn smooth_steering_transitions(
+    time: Res<Time>,
+    mut query: Query<
+        (
+            ChangeTrackers<SteeringBehaviour>,
+            &mut Acceleration,
+            &mut SteeringTransition,
+        ),
+        Without<flight::ManualControl>,
+    >,
+) {
+    for (behaviour_tracker, mut acceleration, mut transition) in &mut query {
+        if behaviour_tracker.is_changed() && !behaviour_tracker.is_added() {
+            transition.from = transition.last;
+            transition.elapsed = 0.;
+        }
+
+        transition.elapsed += time.delta_seconds();
+        acceleration.linear = steering::blend_transition(
+            transition.from,
+            acceleration.linear,
+            transition.elapsed,
+            transition.duration,
+        );
+        transition.last = acceleration.linear;
+    }
+}
+
+/// Smooth acceleration changes of ships having a MaxJerk, after the steering force has been computed
+fn limit_jerk(
+    mut commands: Commands,
+    time: Res<Time>,
     mut query: Query<(
-        &Transform,
-        &Velocity,
-        Option<&MaxVelocity>,
+        Entity,
+        &MaxJerk,
         &mut Acceleration,
-        Option<&MaxAcceleration>,
+        Option<&mut PreviousAcceleration>,
     )>,
-    target_query: Query<&Transform, With<MovementMarker>>,
-    time: Res<Time>,
 ) {
-    let target_tranform = target_query.single();
-
-    for (transform, velocity, max_velocity, mut acceleration, max_acceleration) in &mut query {
-        let difference = target_tranform.translation - transform.translation;
-        let distance = difference.length();
-
-        acceleration.linear = if distance > f32::EPSILON {
-            let max_velocity = max_velocity.map(|m| m.0).unwrap_or(5000.);
-            let max_acceleration = max_acceleration.map(|m| m.0).unwrap_or(200.);
-            let speed = velocity.linear.length();
-            let stop_distance = speed.powi(2) / (2. * max_acceleration);
-            let missalignement =
-                (90. - (difference.angle_between(velocity.linear) * (180. / PI) - 90.).abs()).abs()
-                    / 90.
-                    * 100.;
-
-            (if distance - stop_distance < speed * time.delta_seconds() {
-                // Decelerate before it's too late to stop at the target
-                println!(
-                    "▼ {:05.0}m/s, {:04.0}m/s-2, {:03.0}°",
-                    speed,
-                    acceleration.linear.length(),
-                    missalignement
-                );
-                (difference.normalize_or_zero() / 2. - velocity.linear.normalize_or_zero())
-                    * max_velocity
-            } else if distance < 30. {
-                // Kill the velocity, target reached
-                println!(
-                    "⏹ {:05.0}m/s, {:04.0}m/s-2, {:03.0}°",
-                    speed,
-                    acceleration.linear.length(),
-                    missalignement
-                );
-                velocity.linear * -1.
-            } else if missalignement > 2.0 {
-                // Align with the target if needed
-                println!(
-                    "🗘 {:05.0}m/s, {:04.0}m/s-2, {:03.0}°",
-                    speed,
-                    acceleration.linear.length(),
-                    missalignement
-                );
-                (difference - velocity.linear * 15.).normalize_or_zero() * max_velocity
+    for (entity, max_jerk, mut acceleration, previous) in &mut query {
+        let previous_linear = previous.as_ref().map(|p| p.0).unwrap_or(Vec3::ZERO);
+        acceleration.linear = steering::limit_jerk(
+            previous_linear,
+            acceleration.linear,
+            max_jerk.0,
+            time.delta_seconds(),
+        );
+
+        match previous {
+            Some(mut previous) => previous.0 = acceleration.linear,
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(PreviousAcceleration(acceleration.linear));
+            }
+        }
+    }
+}
+
+/// Adjust the commanded speed of the next order with its wheel binding, Ctrl + mouse wheel by default
+fn adjust_commanded_speed(
+    keys: Res<Input<KeyCode>>,
+    key_bindings: Res<bindings::KeyBindings>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut commanded_speed: ResMut<CommandedSpeed>,
+    cursor_mode: Res<CursorMode>,
+    mut camera_query: Query<&mut PanCam>,
+) {
+    let adjusting = key_bindings.wheel_active(&keys, bindings::Action::CommandedSpeed);
+
+    // PanCam also zooms on mouse wheel, keep it quiet while the speed is adjusted or a menu is open
+    for mut pan_cam in &mut camera_query {
+        pan_cam.enabled = !adjusting && *cursor_mode != CursorMode::Menu;
+    }
+
+    for event in wheel_events.iter() {
+        if adjusting {
+            let step = if event.y > 0. {
+                0.05
+            } else if event.y < 0. {
+                -0.05
             } else {
-                // Go torward the target as fast as posible
-                println!(
-                    "▲ {:05.0}m/s, {:04.0}m/s-2, {:03.0}°",
-                    speed,
-                    acceleration.linear.length(),
-                    missalignement
-                );
-                difference.normalize_or_zero() * max_velocity - velocity.linear
-            })
-            .clamp_length_max(max_acceleration)
-        } else {
-            Vec3::ZERO
-        };
+                0.
+            };
+            commanded_speed.0 = (commanded_speed.0 + step).clamp(0.05, 1.);
+        }
     }
 }
 
-/// Move the movement marker on mouse right click
+/// Show the commanded speed next to the cursor while it is being adjusted or differs from full speed
+fn commanded_speed_label(
+    keys: Res<Input<KeyCode>>,
+    key_bindings: Res<bindings::KeyBindings>,
+    commanded_speed: Res<CommandedSpeed>,
+    mouse_screen_position: Res<MouseScreenPosition>,
+    mut query: Query<(&mut Text, &mut Style, &mut Visibility), With<CommandedSpeedLabel>>,
+) {
+    let (mut text, mut style, mut visibility) = query.single_mut();
+    let adjusting = key_bindings.wheel_active(&keys, bindings::Action::CommandedSpeed);
+
+    match mouse_screen_position.0 {
+        Some(position) if adjusting || commanded_speed.0 < 1. => {
+            visibility.is_visible = true;
+            text.sections[0].value = format!("{:.0}%", commanded_speed.0 * 100.);
+            style.position.left = Val::Px(position.x + 16.);
+            style.position.bottom = Val::Px(position.y - 16.);
+        }
+        _ => visibility.is_visible = false,
+    }
+}
+
+/// Move the movement marker on mouse right click release, applying the commanded speed to the order
+///
+/// The order is previewed while the button is held and can be cancelled with Esc before releasing it
 fn move_movement_marker_on_click(
-    mut target_query: Query<&mut Transform, With<MovementMarker>>,
+    mut commands: Commands,
+    mut target_query: Query<(Entity, &mut Transform), With<MovementMarker>>,
     mouse_world_position: Res<MouseWorldPosition>,
     buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    key_bindings: Res<bindings::KeyBindings>,
+    mut commanded_speed: ResMut<CommandedSpeed>,
+    mut order_preview: ResMut<OrderPreview>,
+    cursor_mode: Res<CursorMode>,
+    time: Res<Time>,
+    window_focus: Res<focus::WindowFocus>,
+    ship_query: Query<
+        (Entity, &Transform, Option<&CollisionShape>),
+        (With<Spaceship>, Without<MovementMarker>),
+    >,
+    obstacle_query: Query<
+        (&Transform, &CollisionShape),
+        (Without<Spaceship>, Without<MovementMarker>),
+    >,
+    debug_draw_config: Res<debug::DebugDrawConfig>,
+    asset_server: Res<AssetServer>,
+    // Missing in fast start, the audio plugin is not added
+    audio: Option<Res<bevy_kira_audio::Audio>>,
 ) {
-    if buttons.just_released(MouseButton::Right) {
-        let mut target_tranform = target_query.single_mut();
+    // A click leaving a modal tool must not be taken as an order
+    if *cursor_mode != CursorMode::Orders || cursor_mode.is_changed() {
+        return;
+    }
+
+    // The click bringing the window back to the foreground is not an order
+    if key_bindings.button_just_pressed(&buttons, bindings::Action::Order)
+        && !window_focus.swallows_clicks(time.seconds_since_startup())
+    {
+        *order_preview = OrderPreview::Holding;
+    }
+
+    if *order_preview == OrderPreview::Holding
+        && key_bindings.just_pressed(&keys, bindings::Action::Cancel)
+    {
+        *order_preview = OrderPreview::Cancelled;
+    }
+
+    if key_bindings.button_just_released(&buttons, bindings::Action::Order) {
+        // Only a press seen by this system makes an order, not a cancelled or swallowed one
+        let holding = *order_preview == OrderPreview::Holding;
+        *order_preview = OrderPreview::Idle;
+        if !holding {
+            return;
+        }
+
+        let strafe = key_bindings.pressed(&keys, bindings::Action::Strafe);
+        let (target, mut target_tranform) = target_query.single_mut();
         target_tranform.translation = mouse_world_position
             .0
             .unwrap_or(target_tranform.translation);
 
-        println!("Moved target to {:?}", target_tranform.translation);
+        if let Some((_, ship_transform, ship_shape)) = ship_query.iter().next() {
+            target_tranform.translation = order_destination(
+                target_tranform.translation,
+                ship_transform.translation,
+                ship_shape,
+                obstacle_query.iter(),
+            );
+        }
+
+        if commanded_speed.0 < 1. {
+            commands
+                .entity(target)
+                .insert(OrderSpeed(commanded_speed.0));
+        } else {
+            commands.entity(target).remove::<OrderSpeed>();
+        }
+
+        debug!(
+            "Moved target to {:?} at {:.0}% speed",
+            target_tranform.translation,
+            commanded_speed.0 * 100.
+        );
+
+        // The commanded speed only applies to the next order
+        *commanded_speed = CommandedSpeed::default();
+
+        visuals::spawn_ping(
+            &mut commands,
+            target_tranform.translation,
+            debug_draw_config.ping_color,
+        );
+        if let Some(audio) = audio {
+            audio
+                .play(asset_server.load("order.ogg"))
+                .with_volume(ORDER_SOUND_VOLUME);
+        }
+        for (ship, ship_transform, _) in &ship_query {
+            commands.entity(ship).insert(visuals::OrderFlash::default());
+            if strafe {
+                commands.entity(ship).insert(stance::Strafe {
+                    heading: ship_transform.rotation,
+                });
+            } else {
+                commands.entity(ship).remove::<stance::Strafe>();
+            }
+        }
     }
 }
 
+/// Where an order clicked at `clicked` sends a ship: next to the surface of the object under the cursor rather than into its center
+fn order_destination<'a>(
+    clicked: Vec3,
+    ship_position: Vec3,
+    ship_shape: Option<&CollisionShape>,
+    obstacles: impl IntoIterator<Item = (&'a Transform, &'a CollisionShape)>,
+) -> Vec3 {
+    let clicked_obstacle = obstacles.into_iter().find(|(transform, shape)| {
+        transform
+            .translation
+            .truncate()
+            .distance(clicked.truncate())
+            < build::bounding_radius(shape)
+    });
+
+    match clicked_obstacle {
+        Some((obstacle_transform, obstacle_shape)) => steering::approach_point(
+            ship_position,
+            obstacle_transform.translation,
+            build::bounding_radius(obstacle_shape),
+            ship_shape.map(build::bounding_radius).unwrap_or(0.),
+            APPROACH_MARGIN,
+        ),
+        None => clicked,
+    }
+}
+
+/// Draw the planned path and estimated time of arrival of the spaceship while the order button is held
+fn order_preview(
+    order_preview: Res<OrderPreview>,
+    commanded_speed: Res<CommandedSpeed>,
+    mouse_screen_position: Res<MouseScreenPosition>,
+    mouse_world_position: Res<MouseWorldPosition>,
+    keys: Res<Input<KeyCode>>,
+    key_bindings: Res<bindings::KeyBindings>,
+    query: Query<
+        (
+            &SteeringBehaviour,
+            &Transform,
+            &Velocity,
+            Option<&MaxVelocity>,
+            Option<&MaxAcceleration>,
+            Option<&flight::InertiaDampener>,
+            Option<&zones::SpeedCap>,
+            Option<&CollisionShape>,
+        ),
+        With<Spaceship>,
+    >,
+    obstacle_query: Query<
+        (&Transform, &CollisionShape),
+        (Without<Spaceship>, Without<MovementMarker>),
+    >,
+    debug_draw_config: Res<debug::DebugDrawConfig>,
+    localization: Res<localization::Localization>,
+    mut lines: ResMut<DebugLines>,
+    mut label_query: Query<(&mut Text, &mut Style, &mut Visibility), With<OrderPreviewLabel>>,
+) {
+    let (mut text, mut style, mut visibility) = label_query.single_mut();
+
+    let (screen_position, clicked) = match (
+        &*order_preview,
+        mouse_screen_position.0,
+        mouse_world_position.0,
+    ) {
+        (OrderPreview::Holding, Some(screen_position), Some(clicked)) => (screen_position, clicked),
+        _ => {
+            visibility.is_visible = false;
+            return;
+        }
+    };
+
+    let strafe = key_bindings.pressed(&keys, bindings::Action::Strafe);
+
+    for (
+        behaviour,
+        transform,
+        velocity,
+        max_velocity,
+        max_acceleration,
+        dampener,
+        speed_cap,
+        shape,
+    ) in &query
+    {
+        // The same destination as the order will get once the button is released
+        let target =
+            order_destination(clicked, transform.translation, shape, obstacle_query.iter());
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: velocity.linear,
+        };
+        // Capped like the steering system does, so that orders through speed zones are not estimated at full speed
+        let max_velocity = max_velocity
+            .map(|m| m.0)
+            .unwrap_or(1000.)
+            .min(speed_cap.map(|c| c.speed).unwrap_or(f32::INFINITY))
+            * commanded_speed.0;
+        let max_acceleration =
+            flight::available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+
+        // Same pure steering functions as the steering system, so the preview never disagrees with the flight
+        let eta = steering::arrival_time(agent, target, ORDER_COMPLETION_DISTANCE, 600., |agent| {
+            match behaviour {
+                SteeringBehaviour::Seek { .. } if !strafe => {
+                    steering::seek(agent, target, max_velocity, max_acceleration)
+                }
+                _ => steering::arrive(agent, target, max_velocity, max_acceleration),
+            }
+        });
+
+        lines.line_colored(
+            transform.translation,
+            target,
+            0.,
+            debug_draw_config.order_preview_color,
+        );
+
+        visibility.is_visible = true;
+        text.sections[0].value = match eta {
+            Some(eta) => format!(
+                "{} {}",
+                localization.get("hud.eta"),
+                format::format_duration(eta, &localization)
+            ),
+            None => format!("{} —", localization.get("hud.eta")),
+        };
+        text.sections[0].value += &format!(
+            " · {}",
+            format::format_distance(transform.translation.distance(target), &localization)
+        );
+        if commanded_speed.0 < 1. {
+            text.sections[0].value += &format!(
+                " {} {:.0}%",
+                localization.get("hud.at_speed"),
+                commanded_speed.0 * 100.
+            );
+        }
+        style.position.left = Val::Px(screen_position.x + 16.);
+        style.position.bottom = Val::Px(screen_position.y + 8.);
+    }
+}
+
+/// Clear per-order parameters once a ship reached its movement marker
+fn complete_orders(
+    mut commands: Commands,
+    query: Query<(
+        Entity,
+        &SteeringBehaviour,
+        &Transform,
+        Option<&stance::Strafe>,
+    )>,
+    target_query: Query<(&Transform, Option<&OrderSpeed>), With<MovementMarker>>,
+) {
+    for (entity, behaviour, transform, strafe) in &query {
+        if let SteeringBehaviour::Seek { target } | SteeringBehaviour::Arrive { target, .. } =
+            behaviour
+        {
+            if let Ok((target_transform, order_speed)) = target_query.get(*target) {
+                if target_transform.translation.distance(transform.translation)
+                    < ORDER_COMPLETION_DISTANCE
+                {
+                    if order_speed.is_some() {
+                        commands.entity(*target).remove::<OrderSpeed>();
+                    }
+                    if strafe.is_some() {
+                        commands.entity(entity).remove::<stance::Strafe>();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Convert a position in logical pixels (origin at the bottom left of the viewport) into world coordinates
+fn screen_to_world(
+    screen_position: Vec2,
+    logical_viewport_size: Vec2,
+    camera_transform: &GlobalTransform,
+    projection_matrix: Mat4,
+) -> Vec3 {
+    let ndc = (screen_position / logical_viewport_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * projection_matrix.inverse();
+    let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
+
+    world_pos.truncate().extend(0.)
+}
+
 /// Update mouse tracking related resources
 fn track_mouse(
     windows: Res<Windows>,
@@ -545,59 +1188,117 @@ fn track_mouse(
     mut mouse_world_coords: ResMut<MouseWorldPosition>,
 ) {
     let (camera, camera_transform) = query.single();
-    let window = if let RenderTarget::Window(id) = camera.target {
-        windows.get(id).unwrap()
-    } else {
-        windows.get_primary().unwrap()
-    };
+    // Only the cursor over the main camera's window counts, other windows may come and go
+    let window = match camera.target {
+        RenderTarget::Window(id) => windows.get(id),
+        RenderTarget::Image(_) => windows.get_primary(),
+    }
+    // The cursor position is stale while the window is in the background
+    .filter(|window| window.is_focused());
 
-    if let Some(screen_pos) = window.cursor_position() {
-        let window_size = Vec2::new(window.width() as f32, window.height() as f32);
-        let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
-        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-        let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-        let world_pos: Vec2 = world_pos.truncate();
+    if let Some((window, screen_pos)) =
+        window.and_then(|window| Some((window, window.cursor_position()?)))
+    {
+        // The cursor position is in logical pixels, so must be the viewport size. Both are derived from the
+        // current scale factor every frame, which keeps them right when the window moves to another monitor
+        let viewport_size = camera
+            .logical_viewport_size()
+            .unwrap_or_else(|| Vec2::new(window.width(), window.height()));
+        let world_pos = screen_to_world(
+            screen_pos,
+            viewport_size,
+            camera_transform,
+            camera.projection_matrix(),
+        );
 
         mouse_screen_coords.0 = Some(screen_pos);
-        mouse_world_coords.0 = Some(world_pos.extend(0.));
+        mouse_world_coords.0 = Some(world_pos);
     } else {
         mouse_screen_coords.0 = None;
         mouse_world_coords.0 = None;
     }
 }
 
-fn debug_velocity(query: Query<(&Transform, &Velocity)>, mut lines: ResMut<DebugLines>) {
-    for (transform, velocity) in &query {
-        let start = transform.translation;
-        let end = start + velocity.linear;
-        lines.line_colored(start, end, 0., Color::YELLOW);
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::camera::CameraProjection;
+
+    #[test]
+    fn screen_to_world_is_independent_of_the_scale_factor() {
+        let physical_size = Vec2::new(1920., 1080.);
+        let camera_transform = GlobalTransform::from_xyz(100., -50., 999.9);
 
-fn debug_acceleration(query: Query<(&Transform, &Acceleration)>, mut lines: ResMut<DebugLines>) {
-    for (transform, acceleration) in &query {
-        let start = transform.translation;
-        let end = start + acceleration.linear;
-        lines.line_colored(start, start + (start - end), 0., Color::BLUE);
+        for scale_factor in [1.0, 1.5, 2.0] {
+            // The camera lays its projection out in logical pixels, the cursor is given in the same unit
+            let logical_size = physical_size / scale_factor;
+            let mut projection = OrthographicProjection {
+                scale: 2.,
+                ..default()
+            };
+            projection.update(logical_size.x, logical_size.y);
+            let to_world = |screen: Vec2| {
+                screen_to_world(
+                    screen,
+                    logical_size,
+                    &camera_transform,
+                    projection.get_projection_matrix(),
+                )
+            };
+
+            for (screen, expected) in [
+                (logical_size / 2., Vec3::new(100., -50., 0.)),
+                (
+                    Vec2::ZERO,
+                    Vec3::new(100. - logical_size.x, -50. - logical_size.y, 0.),
+                ),
+                (
+                    logical_size,
+                    Vec3::new(100. + logical_size.x, -50. + logical_size.y, 0.),
+                ),
+            ] {
+                let world = to_world(screen);
+                assert!(
+                    world.abs_diff_eq(expected, 1e-2),
+                    "{:?} mapped to {:?} instead of {:?} at a scale factor of {}",
+                    screen,
+                    world,
+                    expected,
+                    scale_factor
+                );
+            }
+        }
     }
-}
 
-/// Draw a crosshair on MovementMarker position
-fn debug_movement_marker(
-    target_query: Query<&Transform, With<MovementMarker>>,
-    mut lines: ResMut<DebugLines>,
-) {
-    let target_tranform = target_query.single();
-    lines.line_colored(
-        target_tranform.translation + Vec3::NEG_X * 10.,
-        target_tranform.translation + Vec3::X * 10.,
-        0.,
-        Color::RED,
-    );
-    lines.line_colored(
-        target_tranform.translation + Vec3::NEG_Y * 10.,
-        target_tranform.translation + Vec3::Y * 10.,
-        0.,
-        Color::RED,
-    );
+    #[test]
+    fn strafing_ships_keep_their_heading() {
+        let mut world = World::new();
+        let heading = Quat::from_rotation_z(PI / 4.);
+        // Knocked off its heading by a collision, and moving sideways
+        let strafing = world
+            .spawn()
+            .insert(Transform::from_rotation(Quat::from_rotation_z(1.)))
+            .insert(Velocity::from_linear(Vec3::X * 300.))
+            .insert(stance::Strafe { heading })
+            .id();
+        let flying = world
+            .spawn()
+            .insert(Transform::default())
+            .insert(Velocity::from_linear(Vec3::X * 300.))
+            .id();
+
+        let mut stage = SystemStage::parallel();
+        stage
+            .add_system(orientation)
+            .add_system(stance::hold_strafe_heading);
+        stage.run(&mut world);
+
+        assert_eq!(world.get::<Transform>(strafing).unwrap().rotation, heading);
+        // Ships not strafing still turn their nose to the velocity
+        assert!(world
+            .get::<Transform>(flying)
+            .unwrap()
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_z(1.5 * PI), 1e-5));
+    }
 }
@@ -0,0 +1,252 @@
+use crate::{
+    bindings::{Action, KeyBindings, KeyReference},
+    focus::WindowFocus,
+    localization::Localization,
+    session::Shutdown,
+    visuals::MotionStretch,
+    CursorMode, OrderPreview,
+};
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use heron::*;
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
+
+#[derive(Component)]
+pub struct PauseMenu;
+
+#[derive(Component, Clone, Copy)]
+pub enum PauseMenuButton {
+    Resume,
+    Toggle(Setting),
+    Quit,
+}
+
+/// Setting switched on and off from the pause menu, its button label showing the current value
+#[derive(Component, Clone, Copy)]
+pub enum Setting {
+    AutoPause,
+    MotionStretch,
+}
+
+impl Setting {
+    fn name_key(self) -> &'static str {
+        match self {
+            Setting::AutoPause => "menu.auto_pause",
+            Setting::MotionStretch => "action.motion_stretch",
+        }
+    }
+}
+
+/// Run criteria of gameplay inputs, which must not reach the world while the pause menu is open
+pub fn not_in_menu(cursor_mode: Res<CursorMode>) -> ShouldRun {
+    if *cursor_mode == CursorMode::Menu {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+/// Open the pause menu with Esc, or close it if already open
+pub fn toggle_pause_menu(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    asset_server: Res<AssetServer>,
+    localization: Res<Localization>,
+    mut cursor_mode: ResMut<CursorMode>,
+    order_preview: Res<OrderPreview>,
+    key_reference: Res<KeyReference>,
+    mut physics_time: ResMut<PhysicsTime>,
+    menu_query: Query<Entity, With<PauseMenu>>,
+) {
+    if !bindings.just_pressed(&keys, Action::Cancel) {
+        return;
+    }
+
+    match *cursor_mode {
+        // Esc also leaves modal tools, cancels orders being prepared and hides the key reference, those come first
+        CursorMode::Orders
+            if !cursor_mode.is_changed()
+                && *order_preview == OrderPreview::Idle
+                && !order_preview.is_changed()
+                && !key_reference.visible
+                && !key_reference.is_changed() =>
+        {
+            open_pause_menu(
+                &mut commands,
+                &asset_server,
+                &localization,
+                &mut cursor_mode,
+                &mut physics_time,
+            );
+        }
+        CursorMode::Menu => close_pause_menu(
+            &mut commands,
+            &mut cursor_mode,
+            &mut physics_time,
+            &menu_query,
+        ),
+        _ => {}
+    }
+}
+
+pub fn pause_menu_buttons(
+    mut commands: Commands,
+    mut cursor_mode: ResMut<CursorMode>,
+    mut physics_time: ResMut<PhysicsTime>,
+    time: Res<Time>,
+    mut shutdown: ResMut<Shutdown>,
+    mut focus: ResMut<WindowFocus>,
+    mut motion_stretch: ResMut<MotionStretch>,
+    mut button_query: Query<(&Interaction, &PauseMenuButton, &mut UiColor), Changed<Interaction>>,
+    menu_query: Query<Entity, With<PauseMenu>>,
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        match interaction {
+            Interaction::Clicked => match button {
+                PauseMenuButton::Resume => close_pause_menu(
+                    &mut commands,
+                    &mut cursor_mode,
+                    &mut physics_time,
+                    &menu_query,
+                ),
+                PauseMenuButton::Toggle(Setting::AutoPause) => focus.auto_pause = !focus.auto_pause,
+                PauseMenuButton::Toggle(Setting::MotionStretch) => {
+                    motion_stretch.enabled = !motion_stretch.enabled
+                }
+                PauseMenuButton::Quit => shutdown.request(time.seconds_since_startup()),
+            },
+            Interaction::Hovered => *color = HOVERED_BUTTON.into(),
+            Interaction::None => *color = NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+/// Pause physics and show the menu, taking the cursor away from the world
+pub fn open_pause_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    localization: &Localization,
+    cursor_mode: &mut CursorMode,
+    physics_time: &mut PhysicsTime,
+) {
+    spawn_pause_menu(commands, asset_server, localization);
+    *cursor_mode = CursorMode::Menu;
+    physics_time.pause();
+}
+
+fn spawn_pause_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    localization: &Localization,
+) {
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.6).into(),
+            ..default()
+        })
+        .insert(PauseMenu)
+        .with_children(|parent| {
+            parent.spawn_bundle(
+                TextBundle::from_section(
+                    localization.get("menu.paused"),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 40.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+
+            for button in [
+                PauseMenuButton::Resume,
+                PauseMenuButton::Toggle(Setting::AutoPause),
+                PauseMenuButton::Toggle(Setting::MotionStretch),
+                PauseMenuButton::Quit,
+            ] {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(220.), Val::Px(50.)),
+                            margin: UiRect::all(Val::Px(8.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: NORMAL_BUTTON.into(),
+                        ..default()
+                    })
+                    .insert(button)
+                    .with_children(|parent| {
+                        let key = match button {
+                            PauseMenuButton::Resume => "menu.resume",
+                            PauseMenuButton::Toggle(setting) => setting.name_key(),
+                            PauseMenuButton::Quit => "menu.quit",
+                        };
+                        let mut label = parent.spawn_bundle(TextBundle::from_section(
+                            localization.get(key),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 24.,
+                                color: Color::WHITE,
+                            },
+                        ));
+                        if let PauseMenuButton::Toggle(setting) = button {
+                            label.insert(setting);
+                        }
+                    });
+            }
+        });
+}
+
+/// Show the current value of settings next to their name in the pause menu
+pub fn pause_menu_setting_labels(
+    localization: Res<Localization>,
+    focus: Res<WindowFocus>,
+    motion_stretch: Res<MotionStretch>,
+    mut query: Query<(&mut Text, &Setting)>,
+) {
+    for (mut text, &setting) in &mut query {
+        let enabled = match setting {
+            Setting::AutoPause => focus.auto_pause,
+            Setting::MotionStretch => motion_stretch.enabled,
+        };
+        let value = format!(
+            "{} {}",
+            localization.get(setting.name_key()),
+            localization.get(if enabled { "hud.on" } else { "hud.off" })
+        );
+        // Compared first, so that the text is only laid out again when the value changes
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn close_pause_menu(
+    commands: &mut Commands,
+    cursor_mode: &mut CursorMode,
+    physics_time: &mut PhysicsTime,
+    menu_query: &Query<Entity, With<PauseMenu>>,
+) {
+    for menu in menu_query {
+        commands.entity(menu).despawn_recursive();
+    }
+    *cursor_mode = CursorMode::Orders;
+    physics_time.resume();
+}
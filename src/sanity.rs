@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use heron::*;
+
+/// Minimum time between two warnings about sanitized values, in seconds
+const WARNING_INTERVAL: f64 = 1.;
+
+/// Catch non-finite physics values on the frame they appear, before heron propagates them
+///
+/// Debug builds panic naming the entity and components, release builds reset the faulty values and warn.
+pub fn guard_non_finite(
+    time: Res<Time>,
+    mut last_warning: Local<Option<f64>>,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        Option<&mut Velocity>,
+        Option<&mut Acceleration>,
+    )>,
+) {
+    for (entity, mut transform, velocity, acceleration) in &mut query {
+        let mut faulty = Vec::new();
+
+        let transform_valid = transform.translation.is_finite()
+            && transform.rotation.is_finite()
+            && transform.scale.is_finite();
+        if !transform_valid {
+            faulty.push("Transform");
+        }
+        let velocity = velocity.filter(|velocity| {
+            !(velocity.linear.is_finite() && Vec3::from(velocity.angular).is_finite())
+        });
+        if velocity.is_some() {
+            faulty.push("Velocity");
+        }
+        let acceleration = acceleration.filter(|acceleration| {
+            !(acceleration.linear.is_finite() && Vec3::from(acceleration.angular).is_finite())
+        });
+        if acceleration.is_some() {
+            faulty.push("Acceleration");
+        }
+
+        if faulty.is_empty() {
+            continue;
+        }
+
+        if cfg!(debug_assertions) {
+            panic!("Non-finite {} on {:?}", faulty.join(", "), entity);
+        }
+
+        if !transform_valid {
+            if !transform.translation.is_finite() {
+                transform.translation = Vec3::ZERO;
+            }
+            if !transform.rotation.is_finite() {
+                transform.rotation = Quat::IDENTITY;
+            }
+            if !transform.scale.is_finite() {
+                transform.scale = Vec3::ONE;
+            }
+        }
+        if let Some(mut velocity) = velocity {
+            *velocity = Velocity::from_linear(Vec3::ZERO);
+        }
+        if let Some(mut acceleration) = acceleration {
+            *acceleration = Acceleration::from_linear(Vec3::ZERO);
+        }
+
+        let now = time.seconds_since_startup();
+        if last_warning.map_or(true, |last| now - last >= WARNING_INTERVAL) {
+            *last_warning = Some(now);
+            warn!("Reset non-finite {} on {:?}", faulty.join(", "), entity);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::MainCamera;
+use bevy::{app::AppExit, ecs::schedule::ShouldRun, prelude::*, window::WindowCloseRequested};
+use bevy_kira_audio::prelude::*;
+use std::{env, fs, process, thread, time::Duration};
+
+/// Camera state saved on exit, restored by --fast-start
+const SESSION_PATH: &str = "session.ron";
+
+/// How long the audio fades out before the game actually exits, in seconds
+const SHUTDOWN_FADE: f64 = 0.3;
+
+/// Longest a shutdown may take before the process is ended regardless
+const SHUTDOWN_WATCHDOG: Duration = Duration::from_secs(2);
+
+/// Command line options
+pub struct LaunchOptions {
+    /// Skip audio and restore the previous camera, to restart quickly while tuning
+    pub fast_start: bool,
+    /// Spawn the steering demo scene (--demo)
+    pub demo: bool,
+}
+
+impl LaunchOptions {
+    pub fn from_args() -> Self {
+        Self {
+            fast_start: env::args().skip(1).any(|arg| arg == "--fast-start"),
+            demo: env::args().skip(1).any(|arg| arg == "--demo"),
+        }
+    }
+}
+
+/// Run criteria of audio systems, the audio plugin is not added in fast start
+pub fn audio_enabled(launch_options: Res<LaunchOptions>) -> ShouldRun {
+    if launch_options.fast_start {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+/// Run criteria of the steering demo scene
+pub fn demo_enabled(launch_options: Res<LaunchOptions>) -> ShouldRun {
+    if launch_options.demo {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Exit requested by closing the window or quitting from the menu, AppExit being sent once the audio faded out
+#[derive(Default)]
+pub struct Shutdown {
+    requested_at: Option<f64>,
+}
+
+impl Shutdown {
+    /// Start shutting down, arming a watchdog that ends the process if the exit does not happen in time
+    pub fn request(&mut self, now: f64) {
+        if self.requested_at.is_some() {
+            return;
+        }
+
+        self.requested_at = Some(now);
+        thread::spawn(|| {
+            thread::sleep(SHUTDOWN_WATCHDOG);
+            error!(
+                "Shutdown took more than {:?}, forcing exit",
+                SHUTDOWN_WATCHDOG
+            );
+            process::exit(1);
+        });
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested_at.is_some()
+    }
+}
+
+/// Take over window close requests (close button, Alt+F4), the window is only closed by the exit once finalized
+pub fn request_shutdown_on_close(
+    mut close_events: EventReader<WindowCloseRequested>,
+    time: Res<Time>,
+    mut shutdown: ResMut<Shutdown>,
+) {
+    if close_events.iter().next().is_some() {
+        shutdown.request(time.seconds_since_startup());
+    }
+}
+
+/// Fade the audio out when shutting down, rather than cutting it with a click
+pub fn fade_out_audio_on_shutdown(shutdown: Res<Shutdown>, audio: Res<bevy_kira_audio::Audio>) {
+    if shutdown.is_changed() && shutdown.is_requested() {
+        audio
+            .stop()
+            .fade_out(AudioTween::linear(Duration::from_secs_f64(SHUTDOWN_FADE)));
+    }
+}
+
+/// Exit once the audio faded out, the systems reading AppExit in the Last stage then save and flush everything
+pub fn exit_after_shutdown(
+    time: Res<Time>,
+    shutdown: Res<Shutdown>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    if let Some(requested_at) = shutdown.requested_at {
+        if time.seconds_since_startup() - requested_at >= SHUTDOWN_FADE {
+            app_exit.send(AppExit);
+        }
+    }
+}
+
+/// Save the camera position and zoom when the game exits
+pub fn save_session(
+    mut exit_events: EventReader<AppExit>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    if exit_events.iter().next().is_none() {
+        return;
+    }
+
+    if let Ok((transform, projection)) = camera_query.get_single() {
+        let session = (
+            transform.translation.x,
+            transform.translation.y,
+            projection.scale,
+        );
+        let result = ron::to_string(&session)
+            .map_err(|error| error.to_string())
+            .and_then(|content| {
+                fs::write(SESSION_PATH, content).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            warn!("Could not save session to {}: {}", SESSION_PATH, error);
+        }
+    }
+}
+
+/// Put the camera back where it was on the previous exit, in fast start only
+pub fn restore_session(
+    launch_options: Res<LaunchOptions>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    if !launch_options.fast_start {
+        return;
+    }
+
+    let session: Result<(f32, f32, f32), String> = fs::read_to_string(SESSION_PATH)
+        .map_err(|error| error.to_string())
+        .and_then(|content| ron::from_str(&content).map_err(|error| error.to_string()));
+    match (session, camera_query.get_single_mut()) {
+        (Ok((x, y, scale)), Ok((mut transform, mut projection))) => {
+            transform.translation.x = x;
+            transform.translation.y = y;
+            projection.scale = scale;
+        }
+        (Err(error), _) => info!("No session restored from {}: {}", SESSION_PATH, error),
+        _ => {}
+    }
+}
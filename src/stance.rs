@@ -0,0 +1,222 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    cleanup::DependsOn,
+    debug, CursorMode, MovementMarker, Spaceship,
+};
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+use sebaka::steering::SteeringBehaviour;
+
+/// Radius of the ring drawn around hold anchors
+const ANCHOR_RING_RADIUS: f32 = 60.;
+
+/// Ship holding its position: it arrives back to the anchor whenever something pushes it away
+#[derive(Component)]
+pub struct HoldPosition {
+    /// Entity marking the held position, steered to with Arrive
+    pub anchor: Entity,
+    /// Behaviour restored when the hold is released
+    pub resume: SteeringBehaviour,
+}
+
+#[derive(Component)]
+pub struct HoldAnchor;
+
+/// Ship translating to its order without turning: it arrives at the marker on lateral thrust while keeping `heading`
+#[derive(Component)]
+pub struct Strafe {
+    pub heading: Quat,
+}
+
+/// Hold the current position of the spaceship with H, pressing it again resumes the previous behaviour
+pub fn toggle_hold_position(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    cursor_mode: Res<CursorMode>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut SteeringBehaviour,
+            Option<&HoldPosition>,
+        ),
+        With<Spaceship>,
+    >,
+) {
+    if *cursor_mode != CursorMode::Orders || !bindings.just_pressed(&keys, Action::HoldPosition) {
+        return;
+    }
+
+    for (entity, transform, mut behaviour, hold) in &mut query {
+        match hold {
+            Some(hold) => release_hold(&mut commands, entity, hold, &mut behaviour),
+            None => {
+                let anchor = commands
+                    .spawn()
+                    .insert_bundle(TransformBundle::from_transform(
+                        Transform::from_translation(transform.translation),
+                    ))
+                    .insert(HoldAnchor)
+                    .insert(DependsOn(entity))
+                    .id();
+                let resume = std::mem::replace(
+                    &mut *behaviour,
+                    SteeringBehaviour::Arrive {
+                        target: anchor,
+                        final_angle: None,
+                    },
+                );
+                commands
+                    .entity(entity)
+                    .insert(HoldPosition { anchor, resume });
+            }
+        }
+    }
+}
+
+/// A new move order releases held positions
+pub fn release_hold_on_order(
+    mut commands: Commands,
+    marker_query: Query<(), (With<MovementMarker>, Changed<Transform>)>,
+    mut query: Query<(Entity, &HoldPosition, &mut SteeringBehaviour)>,
+) {
+    if marker_query.is_empty() {
+        return;
+    }
+
+    for (entity, hold, mut behaviour) in &mut query {
+        release_hold(&mut commands, entity, hold, &mut behaviour);
+    }
+}
+
+fn release_hold(
+    commands: &mut Commands,
+    entity: Entity,
+    hold: &HoldPosition,
+    behaviour: &mut SteeringBehaviour,
+) {
+    *behaviour = hold.resume.clone();
+    commands.entity(hold.anchor).despawn();
+    commands.entity(entity).remove::<HoldPosition>();
+}
+
+/// Keep strafing ships on their heading, whatever collisions did to their rotation
+pub fn hold_strafe_heading(mut query: Query<(&mut Transform, &Strafe)>) {
+    for (mut transform, strafe) in &mut query {
+        if transform.rotation != strafe.heading {
+            transform.rotation = strafe.heading;
+        }
+    }
+}
+
+/// Draw a ring around hold anchors
+pub fn debug_hold_anchors(
+    query: Query<&Transform, With<HoldAnchor>>,
+    config: Res<debug::DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for transform in &query {
+        debug::circle(
+            &mut lines,
+            config.circle_segments,
+            transform.translation,
+            ANCHOR_RING_RADIUS,
+            config.marker_color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press_hold(world: &mut World, stage: &mut SystemStage) {
+        let key = KeyBindings::default().key(Action::HoldPosition).unwrap();
+        world.resource_mut::<Input<KeyCode>>().press(key);
+        stage.run(world);
+        world.resource_mut::<Input<KeyCode>>().clear();
+        world.resource_mut::<Input<KeyCode>>().release(key);
+    }
+
+    fn held_anchor(world: &World, ship: Entity) -> Option<Entity> {
+        world.get::<HoldPosition>(ship).map(|hold| hold.anchor)
+    }
+
+    #[test]
+    fn hold_anchors_the_ship_and_restores_its_behaviour() {
+        let mut world = World::new();
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<KeyBindings>();
+        world.init_resource::<CursorMode>();
+        let marker = world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(Transform::from_xyz(
+                0., 1000., 0.,
+            )))
+            .insert(MovementMarker)
+            .id();
+        let ship = world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(Transform::from_xyz(
+                100., 50., 0.,
+            )))
+            .insert(Spaceship)
+            .insert(SteeringBehaviour::Seek { target: marker })
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(toggle_hold_position)
+            .add_system(release_hold_on_order);
+        // The first run sees every marker as changed
+        stage.run(&mut world);
+
+        press_hold(&mut world, &mut stage);
+        let anchor = held_anchor(&world, ship).expect("ship holding");
+        assert!(world.get::<HoldAnchor>(anchor).is_some());
+        assert_eq!(world.get::<DependsOn>(anchor).unwrap().0, ship);
+        assert_eq!(
+            world.get::<Transform>(anchor).unwrap().translation,
+            Vec3::new(100., 50., 0.)
+        );
+        assert_eq!(
+            world.get::<SteeringBehaviour>(ship).unwrap().target(),
+            Some(anchor)
+        );
+
+        // Knocked away, the ship keeps arriving at the anchor
+        world.get_mut::<Transform>(ship).unwrap().translation = Vec3::new(400., -200., 0.);
+        stage.run(&mut world);
+        assert_eq!(
+            world.get::<SteeringBehaviour>(ship).unwrap().name(),
+            "Arrive"
+        );
+        assert_eq!(
+            world.get::<SteeringBehaviour>(ship).unwrap().target(),
+            Some(anchor)
+        );
+
+        // Pressing the key again resumes the previous behaviour
+        press_hold(&mut world, &mut stage);
+        assert!(world.get::<HoldPosition>(ship).is_none());
+        assert!(world.get_entity(anchor).is_none());
+        assert_eq!(world.get::<SteeringBehaviour>(ship).unwrap().name(), "Seek");
+        assert_eq!(
+            world.get::<SteeringBehaviour>(ship).unwrap().target(),
+            Some(marker)
+        );
+
+        // So does a new move order
+        press_hold(&mut world, &mut stage);
+        let anchor = held_anchor(&world, ship).expect("ship holding");
+        world.get_mut::<Transform>(marker).unwrap().translation = Vec3::new(-500., 0., 0.);
+        stage.run(&mut world);
+        assert!(world.get::<HoldPosition>(ship).is_none());
+        assert!(world.get_entity(anchor).is_none());
+        assert_eq!(
+            world.get::<SteeringBehaviour>(ship).unwrap().target(),
+            Some(marker)
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 use heron::*;
+use std::collections::HashMap;
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub enum SteeringBehaviour {
     /// Go to the target at full speed
     Seek { target: Entity },
@@ -41,8 +42,57 @@ pub enum SteeringBehaviour {
 
     /// Hide from target, getting any obstacle between us
     Hide { target: Entity },
+
+    /// Hold a position relative to a leader, `offset` being in the leader's frame (x starboard, y forward)
+    OffsetPursuit { leader: Entity, offset: Vec3 },
 }
 
+impl SteeringBehaviour {
+    /// Name of the behaviour variant, for debugging and telemetry
+    pub fn name(&self) -> &'static str {
+        match self {
+            SteeringBehaviour::Seek { .. } => "Seek",
+            SteeringBehaviour::Arrive { .. } => "Arrive",
+            SteeringBehaviour::Persue { .. } => "Persue",
+            SteeringBehaviour::Flee { .. } => "Flee",
+            SteeringBehaviour::Evade { .. } => "Evade",
+            SteeringBehaviour::FollowPath { .. } => "FollowPath",
+            SteeringBehaviour::Interpose { .. } => "Interpose",
+            SteeringBehaviour::Hide { .. } => "Hide",
+            SteeringBehaviour::OffsetPursuit { .. } => "OffsetPursuit",
+        }
+    }
+
+    /// Entity the behaviour is steering relative to, if any
+    pub fn target(&self) -> Option<Entity> {
+        match self {
+            SteeringBehaviour::Seek { target }
+            | SteeringBehaviour::Arrive { target, .. }
+            | SteeringBehaviour::Persue { target, .. }
+            | SteeringBehaviour::Flee { target }
+            | SteeringBehaviour::Evade { target, .. }
+            | SteeringBehaviour::Hide { target }
+            | SteeringBehaviour::OffsetPursuit { leader: target, .. } => Some(*target),
+            SteeringBehaviour::Interpose { from_target, .. } => Some(*from_target),
+            SteeringBehaviour::FollowPath { .. } => None,
+        }
+    }
+
+    /// Every entity the behaviour refers to
+    pub fn targets(&self) -> impl Iterator<Item = Entity> {
+        let second = match self {
+            SteeringBehaviour::Interpose { to_target, .. } => Some(*to_target),
+            _ => None,
+        };
+
+        self.target().into_iter().chain(second)
+    }
+}
+
+/// Something big enough to hide behind
+#[derive(Component)]
+pub struct Obstacle;
+
 #[derive(Component)]
 pub enum SteeringLimit {
     LinearVelocity { min: f32, max: f32 },
@@ -50,3 +100,806 @@ pub enum SteeringLimit {
     AngularVelocity { min: f32, max: f32 },
     AngularAcceleration { min: f32, max: f32 },
 }
+
+/// Kinematic state of a body, as seen by the steering functions
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Kinematics {
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+/// Steering acceleration to go to the target at full speed
+pub fn seek(agent: Kinematics, target: Vec3, max_velocity: f32, max_acceleration: f32) -> Vec3 {
+    let difference = target - agent.position;
+    let desired_velocity = difference.normalize_or_zero() * max_velocity;
+
+    (desired_velocity - agent.velocity).clamp_length_max(max_acceleration)
+}
+
+/// Steering acceleration to go away from the target at full speed
+pub fn flee(agent: Kinematics, target: Vec3, max_velocity: f32, max_acceleration: f32) -> Vec3 {
+    let difference = agent.position - target;
+    // Right on top of the target any direction will do, as long as it does not change from frame to frame
+    let direction = if difference.length_squared() > f32::EPSILON {
+        difference.normalize()
+    } else if agent.velocity.length_squared() > f32::EPSILON {
+        agent.velocity.normalize()
+    } else {
+        Vec3::Y
+    };
+
+    (direction * max_velocity - agent.velocity).clamp_length_max(max_acceleration)
+}
+
+/// Steering acceleration to go to the target, braking harder as we get closer
+pub fn arrive(agent: Kinematics, target: Vec3, max_velocity: f32, max_acceleration: f32) -> Vec3 {
+    arrive_moving(
+        agent,
+        Kinematics {
+            position: target,
+            velocity: Vec3::ZERO,
+        },
+        max_velocity,
+        max_acceleration,
+    )
+}
+
+/// Arrive on a moving target, ending up matching its velocity: the braking is computed in the target's frame
+///
+/// The desired speed never exceeds what can still be braked over the remaining distance, so a body coming in above
+/// `max_velocity` starts braking early enough too.
+pub fn arrive_moving(
+    agent: Kinematics,
+    target: Kinematics,
+    max_velocity: f32,
+    max_acceleration: f32,
+) -> Vec3 {
+    let difference = target.position - agent.position;
+    let distance = difference.length();
+    let desired_speed = max_velocity.min(braking_speed(0., distance, max_acceleration));
+    let desired_velocity = difference.normalize_or_zero() * desired_speed;
+    let relative_velocity = agent.velocity - target.velocity;
+
+    (desired_velocity - relative_velocity * (1. + relative_velocity.length() / distance.max(1.)))
+        .clamp_length_max(max_acceleration)
+}
+
+/// Steering acceleration to hold `offset`, given in the leader's frame, rotating with the leader as it turns
+pub fn offset_pursuit(
+    agent: Kinematics,
+    leader: Kinematics,
+    leader_rotation: Quat,
+    offset: Vec3,
+    max_velocity: f32,
+    max_acceleration: f32,
+) -> Vec3 {
+    let slot = Kinematics {
+        position: leader.position + leader_rotation * offset,
+        velocity: leader.velocity,
+    };
+
+    arrive_moving(agent, slot, max_velocity, max_acceleration)
+}
+
+/// Steering acceleration to intercept a moving target, seeking where it will be by the time we get there at full speed
+///
+/// Within `min_distance` of the target, match its velocity instead of closing in.
+pub fn persue(
+    agent: Kinematics,
+    target: Kinematics,
+    min_distance: Option<f32>,
+    max_velocity: f32,
+    max_acceleration: f32,
+) -> Vec3 {
+    let distance = agent.position.distance(target.position);
+    if min_distance.map_or(false, |min_distance| distance <= min_distance) {
+        return (target.velocity - agent.velocity).clamp_length_max(max_acceleration);
+    }
+
+    let prediction = distance / max_velocity.max(f32::EPSILON);
+    seek(
+        agent,
+        target.position + target.velocity * prediction,
+        max_velocity,
+        max_acceleration,
+    )
+}
+
+/// Steering acceleration to get between two moving targets, arriving at their midpoint as predicted for when we get there
+pub fn interpose(
+    agent: Kinematics,
+    from: Kinematics,
+    to: Kinematics,
+    max_velocity: f32,
+    max_acceleration: f32,
+) -> Vec3 {
+    let midpoint = (from.position + to.position) / 2.;
+    let travel_time = agent.position.distance(midpoint) / max_velocity.max(f32::EPSILON);
+    let predicted_from = from.position + from.velocity * travel_time;
+    let predicted_to = to.position + to.velocity * travel_time;
+
+    arrive(
+        agent,
+        (predicted_from + predicted_to) / 2.,
+        max_velocity,
+        max_acceleration,
+    )
+}
+
+/// Distance under which Evade reacts to its threat when no min_distance is given
+pub const DEFAULT_EVADE_DISTANCE: f32 = 500.;
+
+/// Steering acceleration to flee from where a moving threat will be, fading out to nothing at `panic_distance` so that we coast once safe
+pub fn evade(
+    agent: Kinematics,
+    threat: Kinematics,
+    panic_distance: f32,
+    max_velocity: f32,
+    max_acceleration: f32,
+) -> Vec3 {
+    let distance = agent.position.distance(threat.position);
+    if distance >= panic_distance {
+        return Vec3::ZERO;
+    }
+
+    let prediction = distance / max_velocity.max(f32::EPSILON);
+    let urgency = 1. - distance / panic_distance;
+    flee(
+        agent,
+        threat.position + threat.velocity * prediction,
+        max_velocity,
+        max_acceleration,
+    ) * urgency
+}
+
+/// Distance to a waypoint under which FollowPath moves on to the next one
+pub const PATH_ARRIVAL_RADIUS: f32 = 50.;
+
+/// Steering acceleration along a path, seeking each waypoint in turn and arriving on the last one
+///
+/// Returns the index of the waypoint being steered to, advanced past the waypoints already within `arrival_radius`.
+pub fn follow_path(
+    agent: Kinematics,
+    path: &[Vec3],
+    current_index: usize,
+    arrival_radius: f32,
+    max_velocity: f32,
+    max_acceleration: f32,
+) -> (Vec3, usize) {
+    if path.is_empty() {
+        // Nowhere to go, just stop
+        return ((-agent.velocity).clamp_length_max(max_acceleration), 0);
+    }
+
+    let mut index = current_index.min(path.len() - 1);
+    while index + 1 < path.len() && agent.position.distance(path[index]) < arrival_radius {
+        index += 1;
+    }
+
+    let acceleration = if index + 1 == path.len() {
+        arrive(agent, path[index], max_velocity, max_acceleration)
+    } else {
+        seek(agent, path[index], max_velocity, max_acceleration)
+    };
+
+    (acceleration, index)
+}
+
+/// Distance kept between an obstacle surface and the hiding spot behind it
+pub const HIDING_DISTANCE: f32 = 150.;
+
+/// Spot behind an obstacle, as seen from the threat, closest to the agent (None without obstacles)
+///
+/// Obstacles are given as center and radius.
+pub fn hiding_spot(
+    agent_position: Vec3,
+    threat: Vec3,
+    obstacles: impl IntoIterator<Item = (Vec3, f32)>,
+) -> Option<Vec3> {
+    obstacles
+        .into_iter()
+        .map(|(center, radius)| {
+            center + (center - threat).normalize_or_zero() * (radius + HIDING_DISTANCE)
+        })
+        .min_by(|a, b| {
+            a.distance_squared(agent_position)
+                .total_cmp(&b.distance_squared(agent_position))
+        })
+}
+
+/// Lateral acceleration steering away from an obstacle hit `hit_distance` ahead along the velocity, stronger as it gets closer
+pub fn avoidance(
+    velocity: Vec3,
+    hit_normal: Vec3,
+    hit_distance: f32,
+    look_ahead_distance: f32,
+    max_acceleration: f32,
+) -> Vec3 {
+    let heading = velocity.normalize_or_zero();
+    let lateral = hit_normal - heading * hit_normal.dot(heading);
+    // Head-on, the normal has no lateral part: any side will do, as long as it is always the same one
+    let lateral = if lateral.length_squared() > f32::EPSILON {
+        lateral.normalize()
+    } else {
+        Vec3::new(heading.y, -heading.x, 0.)
+    };
+    let urgency = (1. - hit_distance / look_ahead_distance.max(f32::EPSILON)).clamp(0., 1.);
+
+    lateral * urgency * max_acceleration
+}
+
+/// Combine two steering accelerations, the secondary one only getting what the primary one leaves of the budget
+pub fn prioritize(primary: Vec3, secondary: Vec3, max_acceleration: f32) -> Vec3 {
+    let primary = primary.clamp_length_max(max_acceleration);
+    let remaining = (max_acceleration - primary.length()).max(0.);
+
+    primary + secondary.clamp_length_max(remaining)
+}
+
+/// Entity seen by the group behaviours
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbour {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+/// Broad phase for neighbour lookups: entities are bucketed in square cells, so that a lookup only visits the cells in reach
+pub struct NeighbourGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Neighbour>>,
+}
+
+impl NeighbourGrid {
+    pub fn new(cell_size: f32, neighbours: impl IntoIterator<Item = Neighbour>) -> Self {
+        let mut grid = Self {
+            cell_size: cell_size.max(1.),
+            cells: HashMap::new(),
+        };
+        for neighbour in neighbours {
+            let cell = grid.cell(neighbour.position);
+            grid.cells.entry(cell).or_default().push(neighbour);
+        }
+        grid
+    }
+
+    fn cell(&self, position: Vec3) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Neighbours within `radius` of `position`, including the entity at that position if any
+    pub fn within(&self, position: Vec3, radius: f32) -> impl Iterator<Item = &Neighbour> {
+        let reach = (radius / self.cell_size).ceil() as i32;
+        let (x, y) = self.cell(position);
+
+        (x - reach..=x + reach)
+            .flat_map(move |x| (y - reach..=y + reach).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |neighbour| {
+                neighbour.position.distance_squared(position) <= radius * radius
+            })
+    }
+}
+
+/// Repulsion from the neighbours within `radius`, growing as they get closer (about 1 at half the radius)
+pub fn separation<'a>(
+    agent: Entity,
+    position: Vec3,
+    neighbours: impl IntoIterator<Item = &'a Neighbour>,
+    radius: f32,
+) -> Vec3 {
+    neighbours
+        .into_iter()
+        .filter(|neighbour| neighbour.entity != agent)
+        .map(|neighbour| {
+            let difference = position - neighbour.position;
+            let distance = difference.length();
+            if distance > f32::EPSILON {
+                difference / distance * (radius / distance.max(1.) - 1.).max(0.)
+            } else if agent.id() < neighbour.entity.id() {
+                // Stacked exactly, split them apart in a stable way
+                Vec3::X * radius
+            } else {
+                Vec3::NEG_X * radius
+            }
+        })
+        .sum()
+}
+
+/// Pull toward the centroid of the neighbours within `radius`, reaching 1 when it is `radius` away
+pub fn cohesion<'a>(
+    agent: Entity,
+    position: Vec3,
+    neighbours: impl IntoIterator<Item = &'a Neighbour>,
+    radius: f32,
+) -> Vec3 {
+    let (sum, count) = neighbours
+        .into_iter()
+        .filter(|neighbour| neighbour.entity != agent)
+        .fold((Vec3::ZERO, 0), |(sum, count), neighbour| {
+            (sum + neighbour.position, count + 1)
+        });
+    if count == 0 {
+        return Vec3::ZERO;
+    }
+
+    ((sum / count as f32 - position) / radius.max(1.)).clamp_length_max(1.)
+}
+
+/// Steer toward the average velocity of the neighbours, relative to the larger of both speeds
+pub fn alignment<'a>(
+    agent: Entity,
+    velocity: Vec3,
+    neighbours: impl IntoIterator<Item = &'a Neighbour>,
+) -> Vec3 {
+    let (sum, count) = neighbours
+        .into_iter()
+        .filter(|neighbour| neighbour.entity != agent)
+        .fold((Vec3::ZERO, 0), |(sum, count), neighbour| {
+            (sum + neighbour.velocity, count + 1)
+        });
+    if count == 0 {
+        return Vec3::ZERO;
+    }
+
+    let average = sum / count as f32;
+    (average - velocity) / average.length().max(velocity.length()).max(1.)
+}
+
+/// Numerical integration scheme used by the reference simulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    /// Velocity first, then position with the new velocity (what the physics engine does)
+    SemiImplicitEuler,
+
+    /// Second order scheme, averaging the acceleration at both ends of the step
+    VelocityVerlet,
+}
+
+/// One sample of a simulated trajectory
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sample {
+    pub time: f32,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub acceleration: Vec3,
+}
+
+impl Integrator {
+    /// Advance the body by `dt` under the acceleration returned by `steering`, returning the new state and the applied acceleration
+    pub fn step(
+        self,
+        state: Kinematics,
+        dt: f32,
+        steering: impl Fn(Kinematics) -> Vec3,
+    ) -> (Kinematics, Vec3) {
+        let acceleration = steering(state);
+
+        match self {
+            Integrator::SemiImplicitEuler => {
+                let velocity = state.velocity + acceleration * dt;
+                let position = state.position + velocity * dt;
+                (Kinematics { position, velocity }, acceleration)
+            }
+            Integrator::VelocityVerlet => {
+                let position = state.position + state.velocity * dt + 0.5 * acceleration * dt * dt;
+                // Steering depends on velocity, so the end-of-step acceleration is evaluated on a predicted velocity
+                let predicted = Kinematics {
+                    position,
+                    velocity: state.velocity + acceleration * dt,
+                };
+                let next_acceleration = steering(predicted);
+                let velocity = state.velocity + 0.5 * (acceleration + next_acceleration) * dt;
+                (Kinematics { position, velocity }, acceleration)
+            }
+        }
+    }
+}
+
+/// Run a steering function outside of the ECS, sampling the trajectory every step
+pub fn simulate(
+    integrator: Integrator,
+    initial: Kinematics,
+    dt: f32,
+    duration: f32,
+    steering: impl Fn(Kinematics) -> Vec3,
+) -> Vec<Sample> {
+    let steps = (duration / dt).ceil() as usize;
+    let mut samples = Vec::with_capacity(steps);
+    let mut state = initial;
+
+    for step in 0..steps {
+        let (next, acceleration) = integrator.step(state, dt, &steering);
+        samples.push(Sample {
+            time: step as f32 * dt,
+            position: state.position,
+            velocity: state.velocity,
+            acceleration,
+        });
+        state = next;
+    }
+
+    samples
+}
+
+/// Express a world space vector in the hull frame of a body: x is lateral (starboard), y is forward (nose)
+pub fn to_hull_frame(rotation: Quat, world: Vec3) -> Vec2 {
+    (rotation.inverse() * world).truncate()
+}
+
+/// Signed angle between the nose and the velocity, positive when sliding to starboard (zero when still)
+pub fn slip_angle(rotation: Quat, velocity: Vec3) -> f32 {
+    let local = to_hull_frame(rotation, velocity);
+    if local.length_squared() > f32::EPSILON {
+        local.x.atan2(local.y)
+    } else {
+        0.
+    }
+}
+
+/// Manual thrust along the input axes, with an optional assist cancelling the drift of idle axes using the remaining thrust budget
+pub fn manual_thrust(
+    input: Vec2,
+    velocity: Vec3,
+    assist: bool,
+    max_acceleration: f32,
+    dt: f32,
+) -> Vec3 {
+    let commanded = input.normalize_or_zero() * max_acceleration;
+    if !assist {
+        return commanded.extend(0.);
+    }
+
+    // Player input has priority, the assist only gets what is left
+    let remaining = (max_acceleration - commanded.length()).max(0.);
+    let dt = dt.max(f32::EPSILON);
+    let brake = Vec2::new(
+        if input.x == 0. { -velocity.x / dt } else { 0. },
+        if input.y == 0. { -velocity.y / dt } else { 0. },
+    )
+    .clamp_length_max(remaining);
+
+    (commanded + brake).extend(0.)
+}
+
+/// Time for a steering function to bring a body within `tolerance` of the target, using the reference integrator (None past `horizon` seconds)
+pub fn arrival_time(
+    agent: Kinematics,
+    target: Vec3,
+    tolerance: f32,
+    horizon: f32,
+    steering: impl Fn(Kinematics) -> Vec3,
+) -> Option<f32> {
+    const DT: f32 = 1. / 30.;
+
+    let mut state = agent;
+    let mut time = 0.;
+    while time < horizon {
+        if state.position.distance(target) < tolerance {
+            return Some(time);
+        }
+        state = Integrator::SemiImplicitEuler.step(state, DT, &steering).0;
+        time += DT;
+    }
+
+    None
+}
+
+/// Blend from the acceleration of a previous behaviour to the current one, `elapsed` seconds into a transition of `duration`
+pub fn blend_transition(from: Vec3, to: Vec3, elapsed: f32, duration: f32) -> Vec3 {
+    if elapsed >= duration {
+        return to;
+    }
+
+    from.lerp(to, (elapsed / duration).max(0.))
+}
+
+/// Slew the acceleration toward the target one, changing it by at most `max_jerk` per second
+pub fn limit_jerk(previous: Vec3, target: Vec3, max_jerk: f32, dt: f32) -> Vec3 {
+    previous + (target - previous).clamp_length_max(max_jerk * dt)
+}
+
+/// Highest speed from which a body can still brake down to `limit` over `distance` (`limit` itself when the distance is negative)
+pub fn braking_speed(limit: f32, distance: f32, max_acceleration: f32) -> f32 {
+    (limit * limit + 2. * max_acceleration * distance.max(0.)).sqrt()
+}
+
+/// Where an approach to a target of `target_radius` should end: on the near side, leaving `margin` between both surfaces (the target itself for point targets)
+pub fn approach_point(
+    agent_position: Vec3,
+    target: Vec3,
+    target_radius: f32,
+    agent_radius: f32,
+    margin: f32,
+) -> Vec3 {
+    if target_radius <= 0. {
+        return target;
+    }
+
+    target + (agent_position - target).normalize_or_zero() * (target_radius + agent_radius + margin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+    /// Headings checked by the hull frame tests, a full turn having to give the same result as none
+    const HEADINGS: [f32; 3] = [0., FRAC_PI_2, TAU];
+
+    #[test]
+    fn hull_frame_follows_the_heading() {
+        for heading in HEADINGS {
+            let rotation = Quat::from_rotation_z(heading);
+            let nose = rotation * Vec3::Y;
+            let starboard = rotation * Vec3::X;
+
+            assert!(
+                to_hull_frame(rotation, nose * 10.).abs_diff_eq(Vec2::new(0., 10.), 1e-4),
+                "heading {}",
+                heading
+            );
+            assert!(
+                to_hull_frame(rotation, starboard * 10.).abs_diff_eq(Vec2::new(10., 0.), 1e-4),
+                "heading {}",
+                heading
+            );
+        }
+
+        // A quarter turn anti-clockwise points the nose to the world -X
+        let rotation = Quat::from_rotation_z(FRAC_PI_2);
+        assert!(to_hull_frame(rotation, Vec3::NEG_X).abs_diff_eq(Vec2::Y, 1e-4));
+        assert!(to_hull_frame(rotation, Vec3::Y).abs_diff_eq(Vec2::X, 1e-4));
+    }
+
+    #[test]
+    fn slip_angle_is_signed_from_the_nose() {
+        for heading in HEADINGS {
+            let rotation = Quat::from_rotation_z(heading);
+            let nose = rotation * Vec3::Y;
+            let starboard = rotation * Vec3::X;
+
+            assert!(slip_angle(rotation, nose * 100.).abs() < 1e-4);
+            assert!((slip_angle(rotation, starboard * 100.) - FRAC_PI_2).abs() < 1e-4);
+            assert!((slip_angle(rotation, -starboard * 100.) + FRAC_PI_2).abs() < 1e-4);
+            assert!((slip_angle(rotation, -nose * 100.).abs() - PI).abs() < 1e-4);
+            assert_eq!(slip_angle(rotation, Vec3::ZERO), 0.);
+        }
+    }
+
+    #[test]
+    fn integrators_agree_on_arrive() {
+        let target = Vec3::Y * 5000.;
+        let run = |integrator| {
+            simulate(integrator, Kinematics::default(), 1. / 60., 60., |agent| {
+                arrive(agent, target, 1000., 100.)
+            })
+        };
+        let euler = run(Integrator::SemiImplicitEuler);
+        let verlet = run(Integrator::VelocityVerlet);
+
+        // The physics engine scheme stays close to the second order reference all along, and both park on the target
+        for (euler, verlet) in euler.iter().zip(&verlet) {
+            assert!(
+                euler.position.distance(verlet.position) < 10.,
+                "diverged at {}s",
+                euler.time
+            );
+        }
+        assert!(euler.last().unwrap().position.distance(target) < 1.);
+        assert!(verlet.last().unwrap().position.distance(target) < 1.);
+    }
+
+    #[test]
+    fn arrive_brakes_in_time_from_above_max_velocity() {
+        // Launched at twice its max velocity, drifting a little sideways
+        let launched = Kinematics {
+            position: Vec3::ZERO,
+            velocity: Vec3::new(50., 2000., 0.),
+        };
+        let target = Vec3::Y * 50_000.;
+        let samples = simulate(
+            Integrator::SemiImplicitEuler,
+            launched,
+            1. / 60.,
+            120.,
+            |agent| arrive(agent, target, 1000., 100.),
+        );
+
+        // Braking from the first frame down to max velocity, then never exceeding it again
+        let speeds: Vec<f32> = samples
+            .iter()
+            .map(|sample| sample.velocity.length())
+            .collect();
+        let capped = speeds
+            .iter()
+            .position(|&speed| speed <= 1000.)
+            .expect("never slowed down to max velocity");
+        assert!(speeds[..=capped].windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(speeds[capped..].iter().all(|&speed| speed <= 1000.));
+
+        // Never past the marker, and staying on it once there instead of orbiting around
+        assert!(samples
+            .iter()
+            .all(|sample| sample.position.y < target.y + 1.));
+        let arrived = samples
+            .iter()
+            .position(|sample| sample.position.distance(target) < 1.)
+            .expect("never reached the marker");
+        assert!(samples[arrived..]
+            .iter()
+            .all(|sample| sample.position.distance(target) < 1.));
+        assert!(samples.last().unwrap().velocity.length() < 1.);
+    }
+
+    #[test]
+    fn flight_assist_stops_a_drifting_ship() {
+        const DT: f32 = 1. / 60.;
+        let drifting = Kinematics {
+            position: Vec3::ZERO,
+            velocity: Vec3::new(300., -200., 0.),
+        };
+        let samples = simulate(Integrator::SemiImplicitEuler, drifting, DT, 5., |agent| {
+            manual_thrust(Vec2::ZERO, agent.velocity, true, 100., DT)
+        });
+
+        // Braking at full thrust without ever overshooting into the opposite drift
+        for pair in samples.windows(2) {
+            assert!(pair[1].velocity.length() <= pair[0].velocity.length());
+        }
+        assert!(samples.last().unwrap().velocity.length() < 1e-3);
+
+        // Without the assist, nothing cancels the drift
+        assert_eq!(
+            manual_thrust(Vec2::ZERO, drifting.velocity, false, 100., DT),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn jerk_limit_bounds_the_change_and_converges() {
+        const DT: f32 = 1. / 60.;
+        const MAX_JERK: f32 = 300.;
+
+        // Full thrust flipping to the opposite side, as on a behaviour change
+        let target = Vec3::new(-100., 0., 0.);
+        let mut acceleration = Vec3::new(100., 0., 0.);
+        let mut steps = 0;
+        while acceleration.distance(target) > 1e-3 {
+            let next = limit_jerk(acceleration, target, MAX_JERK, DT);
+            assert!(next.distance(acceleration) <= MAX_JERK * DT + 1e-4);
+            acceleration = next;
+            steps += 1;
+            assert!(steps <= 41, "not converged after {} steps", steps);
+        }
+
+        // 200 of change at 5 per step
+        assert!(steps >= 40);
+    }
+
+    #[test]
+    fn transition_blend_is_bounded_and_completes() {
+        const DT: f32 = 1. / 60.;
+        const DURATION: f32 = 0.3;
+
+        // Scripted swap from full thrust on one side to full thrust on the other
+        let from = Vec3::new(100., 0., 0.);
+        let to = Vec3::new(-100., 0., 0.);
+        let max_delta = from.distance(to) * DT / DURATION + 1e-3;
+
+        // The steering system resets elapsed on change, then advances it before blending
+        let mut previous = from;
+        let mut elapsed = 0.;
+        for _ in 0..30 {
+            elapsed += DT;
+            let blended = blend_transition(from, to, elapsed, DURATION);
+            assert!(
+                blended.distance(previous) <= max_delta,
+                "jumped by {} at {}s",
+                blended.distance(previous),
+                elapsed
+            );
+            previous = blended;
+        }
+
+        assert_eq!(blend_transition(from, to, DURATION, DURATION), to);
+        assert_eq!(blend_transition(from, to, f32::INFINITY, DURATION), to);
+        assert_eq!(blend_transition(from, to, 0., DURATION), from);
+    }
+
+    #[test]
+    fn degenerate_inputs_give_finite_steering() {
+        let still = Kinematics::default();
+        let moving = Kinematics {
+            position: Vec3::ZERO,
+            velocity: Vec3::new(30., 40., 0.),
+        };
+
+        // Right on top of the target, fleeing picks the velocity direction, or a fixed one when still
+        assert!(flee(still, Vec3::ZERO, 100., 10.).abs_diff_eq(Vec3::Y * 10., 1e-4));
+        assert!(flee(moving, Vec3::ZERO, 100., 10.).abs_diff_eq(Vec3::new(6., 8., 0.), 1e-4));
+        assert!(flee(still, Vec3::ZERO, 0., 10.).is_finite());
+
+        // Arrived and still, nothing to do, and when moving only braking is left
+        assert_eq!(arrive(still, Vec3::ZERO, 100., 10.), Vec3::ZERO);
+        let braking = arrive(moving, Vec3::ZERO, 100., 10.);
+        assert!(braking.is_finite());
+        assert!(braking.dot(moving.velocity) < 0.);
+        // Without any speed allowed, the ship brakes wherever the target is
+        assert!(arrive(moving, Vec3::Y * 1000., 0., 10.).dot(moving.velocity) < 0.);
+        assert_eq!(arrive(still, Vec3::Y * 1000., 0., 10.), Vec3::ZERO);
+
+        // Still, touching or looking nowhere ahead: full lateral push, never NaN
+        for (velocity, hit_distance, look_ahead) in [
+            (Vec3::ZERO, 10., 100.),
+            (Vec3::Y * 100., 0., 100.),
+            (Vec3::Y * 100., 0., 0.),
+        ] {
+            let avoiding = avoidance(velocity, Vec3::NEG_Y, hit_distance, look_ahead, 10.);
+            assert!(avoiding.is_finite());
+            assert!(avoiding.length() <= 10. + 1e-4);
+        }
+        // Head-on, the avoidance still picks a side
+        assert!(avoidance(Vec3::Y * 100., Vec3::NEG_Y, 0., 100., 10.).length() > 9.);
+    }
+
+    #[test]
+    fn held_ship_returns_to_its_anchor() {
+        // Holding is arriving at an anchor dropped where the ship stood, here knocked away by a collision
+        let anchor = Vec3::new(300., -200., 0.);
+        for push in [Vec3::X * 400., Vec3::new(-150., 250., 0.)] {
+            let samples = simulate(
+                Integrator::SemiImplicitEuler,
+                Kinematics {
+                    position: anchor,
+                    velocity: push,
+                },
+                1. / 60.,
+                30.,
+                |agent| arrive(agent, anchor, 1000., 100.),
+            );
+
+            let held = samples.last().unwrap();
+            assert!(
+                held.position.distance(anchor) < 1.,
+                "held {} away after a push of {:?}",
+                held.position.distance(anchor),
+                push
+            );
+            assert!(held.velocity.length() < 1.);
+        }
+    }
+
+    #[test]
+    fn approach_point_stops_short_of_the_surface() {
+        let target = Vec3::new(500., 200., 0.);
+        for agent in [
+            Vec3::ZERO,
+            Vec3::new(900., 200., 0.),
+            Vec3::new(500., -3000., 0.),
+        ] {
+            let point = approach_point(agent, target, 150., 40., 25.);
+
+            assert!(
+                (point.distance(target) - (150. + 40. + 25.)).abs() < 1e-3,
+                "stopped {} from the center",
+                point.distance(target)
+            );
+            // On the near side, the agent does not go around the target
+            assert!(point.distance(agent) < target.distance(agent));
+        }
+    }
+
+    #[test]
+    fn approach_point_keeps_point_targets() {
+        for target_radius in [0., -1.] {
+            let target = Vec3::new(500., 200., 0.);
+            let point = approach_point(Vec3::ZERO, target, target_radius, 40., 25.);
+            assert!(point.abs_diff_eq(target, 1e-6));
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    flight, MaxAcceleration, Spaceship,
+};
+use bevy::{app::AppExit, prelude::*};
+use heron::*;
+use sebaka::steering::SteeringBehaviour;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs,
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const TELEMETRY_DIRECTORY: &str = "telemetry";
+
+const CSV_HEADER: &str =
+    "time,x,y,velocity_x,velocity_y,acceleration_x,acceleration_y,behaviour,distance,thrust\n";
+
+/// Record steering telemetry of this entity until the component is removed or the entity despawned
+#[derive(Component)]
+pub struct Telemetry;
+
+/// In-memory CSV buffers of recorded entities, written to the telemetry directory when recording stops
+#[derive(Default)]
+pub struct TelemetryRecorder {
+    buffers: HashMap<Entity, String>,
+    /// Files written so far, numbering them so that recordings stopped within the same second never overwrite each other
+    written: u32,
+    /// Background writes that may still be running
+    pending: Vec<JoinHandle<()>>,
+}
+
+impl TelemetryRecorder {
+    /// Write the buffer of an entity to a CSV file on a background thread
+    pub fn flush(&mut self, entity: Entity) {
+        if let Some(buffer) = self.buffers.remove(&entity) {
+            self.written += 1;
+            let path = csv_path(entity, self.written);
+            self.pending.retain(|write| !write.is_finished());
+            self.pending
+                .push(thread::spawn(move || write_csv(&path, buffer)));
+        }
+    }
+
+    /// Write every buffer right away and wait for the background writes, a thread would not outlive the process on exit
+    pub fn flush_all(&mut self) {
+        for (entity, buffer) in self.buffers.drain() {
+            self.written += 1;
+            write_csv(&csv_path(entity, self.written), buffer);
+        }
+        for write in self.pending.drain(..) {
+            if write.join().is_err() {
+                error!("A telemetry write panicked");
+            }
+        }
+    }
+}
+
+fn csv_path(entity: Entity, sequence: u32) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    format!(
+        "{}/{}-{}-{}.csv",
+        TELEMETRY_DIRECTORY,
+        entity.id(),
+        timestamp,
+        sequence
+    )
+}
+
+fn write_csv(path: &str, buffer: String) {
+    match fs::create_dir_all(TELEMETRY_DIRECTORY).and_then(|_| fs::write(path, buffer)) {
+        Ok(_) => info!("Telemetry written to {}", path),
+        Err(error) => error!("Could not write telemetry to {}: {}", path, error),
+    }
+}
+
+/// Start or stop recording the spaceship telemetry with F8
+pub fn toggle_telemetry(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    query: Query<(Entity, Option<&Telemetry>), With<Spaceship>>,
+) {
+    if !bindings.just_pressed(&keys, Action::Telemetry) {
+        return;
+    }
+
+    for (entity, telemetry) in &query {
+        if telemetry.is_some() {
+            commands.entity(entity).remove::<Telemetry>();
+            info!("Telemetry stopped for {:?}", entity);
+        } else {
+            commands.entity(entity).insert(Telemetry);
+            info!("Telemetry started for {:?}", entity);
+        }
+    }
+}
+
+/// Append one row per recorded entity
+pub fn record_telemetry(
+    time: Res<Time>,
+    mut recorder: ResMut<TelemetryRecorder>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &Acceleration,
+            Option<&SteeringBehaviour>,
+            Option<&MaxAcceleration>,
+            Option<&flight::InertiaDampener>,
+        ),
+        With<Telemetry>,
+    >,
+    target_query: Query<&Transform>,
+) {
+    for (entity, transform, velocity, acceleration, behaviour, max_acceleration, dampener) in &query
+    {
+        let distance = behaviour
+            .and_then(|behaviour| behaviour.target())
+            .and_then(|target| target_query.get(target).ok())
+            .map(|target| target.translation.distance(transform.translation));
+        // Relative to the thrust actually available, so that full thrust reads 1 with the dampener on too
+        let max_acceleration =
+            flight::available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+
+        recorder
+            .buffers
+            .entry(entity)
+            .or_insert_with(|| CSV_HEADER.to_string())
+            .push_str(&csv_row(
+                time.seconds_since_startup(),
+                transform.translation,
+                velocity.linear,
+                acceleration.linear,
+                behaviour.map(|behaviour| behaviour.name()).unwrap_or(""),
+                distance,
+                acceleration.linear.length() / max_acceleration,
+            ));
+    }
+}
+
+/// One line of the CSV, matching CSV_HEADER (an empty distance when there is no target)
+fn csv_row(
+    time: f64,
+    position: Vec3,
+    velocity: Vec3,
+    acceleration: Vec3,
+    behaviour: &str,
+    distance: Option<f32>,
+    thrust: f32,
+) -> String {
+    let mut row = String::new();
+    // Writing to a String cannot fail
+    let _ = writeln!(
+        row,
+        "{:.4},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{},{},{:.3}",
+        time,
+        position.x,
+        position.y,
+        velocity.x,
+        velocity.y,
+        acceleration.x,
+        acceleration.y,
+        behaviour,
+        distance
+            .map(|distance| format!("{:.3}", distance))
+            .unwrap_or_default(),
+        thrust,
+    );
+    row
+}
+
+/// Write the telemetry of entities that stopped recording or were despawned
+pub fn flush_telemetry(
+    removed: RemovedComponents<Telemetry>,
+    mut recorder: ResMut<TelemetryRecorder>,
+) {
+    for entity in removed.iter() {
+        recorder.flush(entity);
+    }
+}
+
+/// Write the telemetry still being recorded when the game exits, closing the window included
+pub fn flush_telemetry_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut recorder: ResMut<TelemetryRecorder>,
+) {
+    if exit_events.iter().next().is_some() {
+        recorder.flush_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rows_match_the_header() {
+        let row = csv_row(
+            1.5,
+            Vec3::new(10., -20., 0.),
+            Vec3::new(1., 2., 0.),
+            Vec3::new(-3., 4., 0.),
+            "Arrive",
+            Some(123.4567),
+            0.5,
+        );
+
+        assert_eq!(
+            row,
+            "1.5000,10.000,-20.000,1.000,2.000,-3.000,4.000,Arrive,123.457,0.500\n"
+        );
+        assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn rows_without_target_leave_the_distance_empty() {
+        let row = csv_row(0., Vec3::ZERO, Vec3::ZERO, Vec3::ZERO, "", None, 0.);
+
+        assert_eq!(row, "0.0000,0.000,0.000,0.000,0.000,0.000,0.000,,,0.000\n");
+        assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn five_second_recording_has_a_row_per_frame() {
+        const FRAMES: u32 = 300;
+        let dt = 1. / 60.;
+
+        let mut world = World::new();
+        world.insert_resource(Time::default());
+        world.insert_resource(TelemetryRecorder::default());
+        let marker = world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(Transform::from_xyz(
+                0., 2000., 0.,
+            )))
+            .id();
+        let ship = world
+            .spawn()
+            .insert_bundle(TransformBundle::default())
+            .insert(Velocity::default())
+            .insert(Acceleration::default())
+            .insert(SteeringBehaviour::Arrive {
+                target: marker,
+                final_angle: None,
+            })
+            .insert(Telemetry)
+            .id();
+
+        let mut steering = SystemStage::single_threaded();
+        steering.add_system(crate::steering_behaviour);
+        let mut recording = SystemStage::single_threaded();
+        recording.add_system(record_telemetry);
+        let startup = world.resource::<Time>().startup();
+        for frame in 0..FRAMES {
+            world
+                .resource_mut::<Time>()
+                .update_with_instant(startup + Duration::from_secs_f32(frame as f32 * dt));
+            steering.run(&mut world);
+            recording.run(&mut world);
+
+            // Headless, so the physics step is scripted: the integration the reference simulation uses
+            let acceleration = world.get::<Acceleration>(ship).unwrap().linear;
+            let velocity = {
+                let mut velocity = world.get_mut::<Velocity>(ship).unwrap();
+                velocity.linear += acceleration * dt;
+                velocity.linear
+            };
+            world.get_mut::<Transform>(ship).unwrap().translation += velocity * dt;
+        }
+
+        let recorder = world.resource::<TelemetryRecorder>();
+        let mut lines = recorder.buffers[&ship].lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER.trim_end()));
+        let rows: Vec<Vec<&str>> = lines.map(|row| row.split(',').collect()).collect();
+        assert_eq!(rows.len(), FRAMES as usize);
+        let columns = CSV_HEADER.split(',').count();
+        assert!(rows
+            .iter()
+            .all(|row| row.len() == columns && row[7] == "Arrive"));
+        // The ship moved toward the marker during the recording
+        let distance = |row: &Vec<&str>| row[8].parse::<f32>().unwrap();
+        assert!(distance(&rows[rows.len() - 1]) < distance(&rows[0]));
+    }
+}
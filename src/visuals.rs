@@ -0,0 +1,158 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    debug, MaxVelocity,
+};
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+use heron::*;
+use sebaka::steering;
+
+/// Maximum stretch of hull sprites along the nose, reached at MaxVelocity
+const MAX_STRETCH: f32 = 0.15;
+
+/// Speed ratio (of MaxVelocity) under which hull sprites are not stretched, the heading still jitters at low speed
+const STRETCH_THRESHOLD: f32 = 0.2;
+
+/// Stretch hull sprites along their nose at high speed, toggled with F5
+pub struct MotionStretch {
+    pub enabled: bool,
+}
+
+impl Default for MotionStretch {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Hull sprite of a ship, a visual-only child that can be scaled without touching the collider
+#[derive(Component)]
+pub struct HullSprite;
+
+pub fn toggle_motion_stretch(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut motion_stretch: ResMut<MotionStretch>,
+) {
+    if bindings.just_pressed(&keys, Action::MotionStretch) {
+        motion_stretch.enabled = !motion_stretch.enabled;
+    }
+}
+
+/// Scale hull sprites along the nose proportionally to speed, so that fast ships don't look static
+pub fn motion_stretch(
+    motion_stretch: Res<MotionStretch>,
+    ship_query: Query<(&Transform, &Velocity, Option<&MaxVelocity>, &Children)>,
+    mut sprite_query: Query<&mut Transform, (With<HullSprite>, Without<Velocity>)>,
+) {
+    for (transform, velocity, max_velocity, children) in &ship_query {
+        let max_velocity = max_velocity.map(|m| m.0).unwrap_or(1000.);
+        // Only the speed along the nose counts, a sprite cannot be stretched sideways without shearing
+        let forward_speed = steering::to_hull_frame(transform.rotation, velocity.linear)
+            .y
+            .abs();
+        let ratio = ((forward_speed / max_velocity - STRETCH_THRESHOLD) / (1. - STRETCH_THRESHOLD))
+            .clamp(0., 1.);
+        let stretch = if motion_stretch.enabled {
+            1. + MAX_STRETCH * ratio
+        } else {
+            1.
+        };
+
+        for &child in children {
+            if let Ok(mut sprite_transform) = sprite_query.get_mut(child) {
+                sprite_transform.scale = Vec3::new(1., stretch, 1.);
+            }
+        }
+    }
+}
+
+/// Expanding ring drawn around a world position to draw the eye to it, for example on order confirmation
+#[derive(Component)]
+pub struct Ping {
+    color: Color,
+    age: f32,
+}
+
+impl Ping {
+    const LIFETIME: f32 = 0.5;
+    const START_RADIUS: f32 = 20.;
+    const END_RADIUS: f32 = 120.;
+}
+
+/// Ping a world position, every call spawns its own independent ring
+pub fn spawn_ping(commands: &mut Commands, position: Vec3, color: Color) {
+    commands
+        .spawn_bundle(TransformBundle::from_transform(
+            Transform::from_translation(position),
+        ))
+        .insert(Ping { color, age: 0. });
+}
+
+/// Grow and fade pings, despawning them at the end of their life
+pub fn animate_pings(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<debug::DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+    mut query: Query<(Entity, &Transform, &mut Ping)>,
+) {
+    for (entity, transform, mut ping) in &mut query {
+        ping.age += time.delta_seconds();
+        if ping.age >= Ping::LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let progress = ping.age / Ping::LIFETIME;
+        let radius = Ping::START_RADIUS + (Ping::END_RADIUS - Ping::START_RADIUS) * progress;
+        let mut color = ping.color;
+        color.set_a(1. - progress);
+        debug::circle(
+            &mut lines,
+            config.circle_segments,
+            transform.translation,
+            radius,
+            color,
+        );
+    }
+}
+
+/// Brief tint of the hull of ships that just received an order
+#[derive(Component)]
+pub struct OrderFlash {
+    age: f32,
+}
+
+impl OrderFlash {
+    const DURATION: f32 = 0.25;
+    const COLOR: Color = Color::rgb(0.6, 1.0, 0.6);
+}
+
+impl Default for OrderFlash {
+    fn default() -> Self {
+        Self { age: 0. }
+    }
+}
+
+/// Fade the order flash of hull sprites back to their normal color
+pub fn order_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ship_query: Query<(Entity, &mut OrderFlash, &Children)>,
+    mut sprite_query: Query<&mut Sprite, With<HullSprite>>,
+) {
+    for (entity, mut flash, children) in &mut ship_query {
+        flash.age += time.delta_seconds();
+        let progress = (flash.age / OrderFlash::DURATION).min(1.);
+
+        for &child in children {
+            if let Ok(mut sprite) = sprite_query.get_mut(child) {
+                sprite.color = OrderFlash::COLOR * (1. - progress) + Color::WHITE * progress;
+            }
+        }
+
+        if progress >= 1. {
+            commands.entity(entity).remove::<OrderFlash>();
+        }
+    }
+}
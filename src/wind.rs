@@ -0,0 +1,156 @@
+use crate::{
+    bindings::{Action, KeyBindings},
+    build, CursorMode,
+};
+use bevy::prelude::*;
+use heron::*;
+use std::f32::consts::PI;
+
+/// Ambient force pushing every dynamic body, light ones drift while heavy ones barely notice. Off by default.
+pub struct SolarWind {
+    pub enabled: bool,
+    /// Force applied to every body, acceleration being this divided by the body mass
+    pub strength: f32,
+    /// How much direction and strength wander over time, 0 for a constant wind
+    pub variability: f32,
+    pub seed: u32,
+}
+
+impl Default for SolarWind {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strength: 100_000.,
+            variability: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+impl SolarWind {
+    /// Wind force at the given time, varying slowly
+    pub fn force(&self, time: f32) -> Vec3 {
+        let angle = self.variability * PI * (noise(self.seed, time * 0.05) * 2. - 1.);
+        let strength = self.strength
+            * (1. + self.variability * (noise(self.seed.wrapping_add(1), time * 0.1) - 0.5));
+
+        Vec3::new(angle.cos(), angle.sin(), 0.) * strength
+    }
+}
+
+/// Smooth value noise in [0, 1]
+fn noise(seed: u32, t: f32) -> f32 {
+    let hash = |i: i64| {
+        let mut x = (i as u32).wrapping_mul(0x9E37_79B9) ^ seed.wrapping_mul(0x85EB_CA6B);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x2C1B_3C6D);
+        x ^= x >> 12;
+        x as f32 / u32::MAX as f32
+    };
+
+    let i = t.floor();
+    let f = t - i;
+    let smooth = f * f * (3. - 2. * f);
+    hash(i as i64) * (1. - smooth) + hash(i as i64 + 1) * smooth
+}
+
+/// Mass of a body from its bounding circle and material density (heron's default density is 1)
+fn mass(shape: &CollisionShape, material: Option<&PhysicMaterial>) -> f32 {
+    let radius = build::bounding_radius(shape);
+    material.map(|m| m.density).unwrap_or(1.) * PI * radius * radius
+}
+
+pub fn toggle_solar_wind(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut solar_wind: ResMut<SolarWind>,
+) {
+    if bindings.just_pressed(&keys, Action::SolarWind) {
+        solar_wind.enabled = !solar_wind.enabled;
+        info!("Solar wind: {}", solar_wind.enabled);
+    }
+}
+
+/// Push dynamic bodies with the wind. Velocity is changed directly, since steering overwrites Acceleration every frame.
+pub fn apply_solar_wind(
+    solar_wind: Res<SolarWind>,
+    time: Res<Time>,
+    cursor_mode: Res<CursorMode>,
+    mut query: Query<(
+        &RigidBody,
+        &CollisionShape,
+        Option<&PhysicMaterial>,
+        &mut Velocity,
+    )>,
+) {
+    // Physics is paused while a menu is open
+    if !solar_wind.enabled || *cursor_mode == CursorMode::Menu {
+        return;
+    }
+
+    let force = solar_wind.force(time.seconds_since_startup() as f32);
+    for (rigid_body, shape, material, mut velocity) in &mut query {
+        if *rigid_body == RigidBody::Dynamic {
+            velocity.linear += force / mass(shape, material) * time.delta_seconds();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sebaka::steering::{self, Integrator, Kinematics};
+    use std::cell::Cell;
+
+    #[test]
+    fn arrive_parks_on_the_marker_under_solar_wind() {
+        let dt = 1. / 60.;
+        let target = Vec3::Y * 5000.;
+
+        for seed in 0..3 {
+            let wind = SolarWind {
+                enabled: true,
+                seed,
+                ..default()
+            };
+            // The spaceship and the demo drones, with the limits they steer with
+            for (shape, max_velocity, max_acceleration) in [
+                (
+                    CollisionShape::Capsule {
+                        radius: 100.,
+                        half_segment: 25.,
+                    },
+                    1000.,
+                    100.,
+                ),
+                (CollisionShape::Sphere { radius: 50. }, 600., 200.),
+            ] {
+                let mass = mass(&shape, None);
+                // This integrator steers once per step, so the wind wanders with the simulated time as in apply_solar_wind
+                let time = Cell::new(0.);
+                let samples = steering::simulate(
+                    Integrator::SemiImplicitEuler,
+                    Kinematics::default(),
+                    dt,
+                    60.,
+                    |agent| {
+                        let force = wind.force(time.get());
+                        time.set(time.get() + dt);
+                        force / mass
+                            + steering::arrive(agent, target, max_velocity, max_acceleration)
+                    },
+                );
+
+                let parked = samples.last().unwrap();
+                assert!(
+                    parked.position.distance(target) < 1.,
+                    "parked {} away with a mass of {} and a seed of {}",
+                    parked.position.distance(target),
+                    mass,
+                    seed
+                );
+                assert!(parked.velocity.length() < 1.);
+            }
+        }
+    }
+}
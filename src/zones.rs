@@ -0,0 +1,106 @@
+use crate::{
+    debug,
+    flight::{self, ManualControl},
+    MaxAcceleration,
+};
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+use heron::*;
+use sebaka::steering::{self, SteeringBehaviour};
+
+/// Speed limit applying to every ship within `radius` of the entity
+#[derive(Component)]
+pub struct SpeedZone {
+    pub radius: f32,
+    pub limit: f32,
+}
+
+/// Speed a ship may have at its position so that it can still brake down to the limit of every speed zone before entering it
+#[derive(Component)]
+pub struct SpeedCap {
+    pub speed: f32,
+    /// Whether the ship is inside a zone, `speed` then being the zone limit
+    pub inside: bool,
+}
+
+impl Default for SpeedCap {
+    fn default() -> Self {
+        Self {
+            speed: f32::INFINITY,
+            inside: false,
+        }
+    }
+}
+
+/// Give a speed cap to every steered ship lacking one, so that zones apply to every ship and not only the ones spawned with it
+pub fn insert_speed_caps(
+    mut commands: Commands,
+    query: Query<Entity, (With<SteeringBehaviour>, Without<SpeedCap>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(SpeedCap::default());
+    }
+}
+
+/// Compute the speed cap of ships from the surrounding zones, before steering uses it
+///
+/// Braking is planned with the thrust actually available, which the inertia dampener reduces.
+pub fn update_speed_caps(
+    mut query: Query<(
+        &Transform,
+        Option<&MaxAcceleration>,
+        Option<&flight::InertiaDampener>,
+        &mut SpeedCap,
+    )>,
+    zone_query: Query<(&Transform, &SpeedZone)>,
+) {
+    for (transform, max_acceleration, dampener, mut cap) in &mut query {
+        let max_acceleration =
+            flight::available_thrust(max_acceleration.map(|m| m.0).unwrap_or(100.), dampener);
+        let mut speed = f32::INFINITY;
+        let mut inside = false;
+
+        for (zone_transform, zone) in &zone_query {
+            let distance = transform
+                .translation
+                .truncate()
+                .distance(zone_transform.translation.truncate())
+                - zone.radius;
+            inside |= distance <= 0.;
+            speed = speed.min(steering::braking_speed(
+                zone.limit,
+                distance,
+                max_acceleration,
+            ));
+        }
+
+        cap.speed = speed;
+        cap.inside = inside;
+    }
+}
+
+/// Manually flown ships are not anticipating zones, they are clamped to the limit once inside
+pub fn clamp_manual_speed(mut query: Query<(&mut Velocity, &SpeedCap), With<ManualControl>>) {
+    for (mut velocity, cap) in &mut query {
+        if cap.inside {
+            velocity.linear = velocity.linear.clamp_length_max(cap.speed);
+        }
+    }
+}
+
+/// Draw the boundary of speed zones, tinted per station in entity color mode
+pub fn debug_speed_zones(
+    query: Query<(Entity, &Transform, &SpeedZone)>,
+    config: Res<debug::DebugDrawConfig>,
+    mut lines: ResMut<DebugLines>,
+) {
+    for (entity, transform, zone) in &query {
+        debug::circle(
+            &mut lines,
+            config.circle_segments,
+            transform.translation,
+            zone.radius,
+            config.tint(entity, None, config.zone_color, 0.5),
+        );
+    }
+}